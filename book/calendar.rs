@@ -0,0 +1,173 @@
+use crate::book::registry::{Registry, Reservation};
+use crate::book::store::BookStore;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const CHAPTER_URL: &str = "https://www.esjzone.cc/forum";
+// 行事曆中每個發布事件的長度
+const EVENT_MINUTES: i64 = 30;
+const UID_DOMAIN: &str = "auto-esj";
+const USAGE: &str = "用法: calendar [書籍編號或稿件路徑] [--output <檔案>]";
+
+// RFC 5545 文字欄位需跳脫反斜線、分號、逗號與換行
+fn escape(text: &str) -> String {
+    text.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace('\n', "\\n")
+}
+
+// 每行不超過 75 位元組，續行以空白開頭，不可切斷 UTF-8 字元
+fn fold(line: &str) -> String {
+    let mut folded = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+fn timestamp(datetime: DateTime<Utc>) -> String {
+    datetime.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+// UID 只由書籍編號與章節編號組成，重新匯出時同一章節的事件會被更新而不是重複
+fn event(reservation: &Reservation, url: Option<&str>, now: DateTime<Utc>) -> Option<String> {
+    let start = reservation.datetime()?.with_timezone(&Utc);
+    let mut description = format!("書籍 {} 第 {} 章", reservation.book_id(), reservation.index());
+    let mut lines = vec![
+        "BEGIN:VEVENT".to_string(),
+        format!("UID:{}-{}@{}", reservation.book_id(), reservation.index(), UID_DOMAIN),
+        format!("DTSTAMP:{}", timestamp(now)),
+        format!("DTSTART:{}", timestamp(start)),
+        format!("DTEND:{}", timestamp(start + Duration::minutes(EVENT_MINUTES))),
+        format!("SUMMARY:{}", escape(&format!("[{}] {}", reservation.book_id(), reservation.title()))),
+        format!("CATEGORIES:{}", escape(reservation.book_id())),
+    ];
+    if let Some(url) = url {
+        description.push('\n');
+        description.push_str(url);
+        lines.push(format!("URL:{}", url));
+    }
+    lines.push(format!("DESCRIPTION:{}", escape(&description)));
+    lines.push("END:VEVENT".to_string());
+    Some(lines.iter().map(|line| fold(line)).collect())
+}
+
+// 遠端章節 ID 為純數字，其他內容 (例如手動修改的章節列表) 不組成網址
+fn chapter_url(book_id: &str, remote_id: &str) -> Option<String> {
+    if remote_id.is_empty() || !remote_id.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    Some(format!("{}/{}/{}.html", CHAPTER_URL, book_id, remote_id))
+}
+
+// urls: (書籍編號, 章節編號) -> 章節網址，只有已取得遠端章節 ID 的章節才有；回傳行事曆與實際寫入的事件數
+pub fn build(reservations: &[&Reservation], urls: &HashMap<(String, usize), String>) -> (String, usize) {
+    let now = Utc::now();
+    let mut calendar = String::new();
+    for line in ["BEGIN:VCALENDAR", "VERSION:2.0", "PRODID:-//auto-esj//publish schedule//ZH", "CALSCALE:GREGORIAN"] {
+        calendar.push_str(&fold(line));
+    }
+    let mut count = 0;
+    for reservation in reservations {
+        let key = (reservation.book_id().to_string(), reservation.index());
+        if let Some(event) = event(reservation, urls.get(&key).map(String::as_str), now) {
+            calendar.push_str(&event);
+            count += 1;
+        }
+    }
+    calendar.push_str(&fold("END:VCALENDAR"));
+    (calendar, count)
+}
+
+// 匯出指定書籍 (None 為所有書籍) 的所有預約，回傳寫入的事件數 (沒有預約時間的記錄不計)
+pub fn export(book_id: Option<&str>, path: &Path) -> Result<usize> {
+    let registry = Registry::load()?;
+    let store = BookStore::read()?;
+    let reservations: Vec<&Reservation> = registry.reservations()
+        .iter()
+        .filter(|reservation| book_id.is_none_or(|id| reservation.book_id() == id))
+        .collect();
+
+    let mut urls = HashMap::new();
+    for (id, config) in store.books() {
+        if book_id.is_some_and(|book_id| book_id != id) {
+            continue;
+        }
+        for entry in config.ledger()?.entries() {
+            let Some(remote_id) = entry.remote_id() else {
+                continue;
+            };
+            match chapter_url(id, remote_id) {
+                Some(url) => {
+                    urls.insert((id.clone(), entry.index()), url);
+                }
+                None => println!("警告：書籍 {} 第 {} 章的遠端章節 ID「{}」格式錯誤，不加入網址", id, entry.index(), remote_id),
+            }
+        }
+    }
+
+    let (calendar, count) = build(&reservations, &urls);
+    fs::write(path, calendar)
+        .with_context(|| format!("寫入行事曆 {} 失敗", path.display()))?;
+    Ok(count)
+}
+
+// 命令列：匯出單一書籍或所有書籍的發布行事曆
+pub fn export_calendar(args: &[String]) -> Result<()> {
+    let mut target = None;
+    let mut output = None;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--output" | "-o" => output = Some(PathBuf::from(iter.next().ok_or_else(|| anyhow!("{} 缺少參數值", arg))?)),
+            _ if target.is_none() => target = Some(arg.as_str()),
+            _ => return Err(anyhow!("{}", USAGE)),
+        }
+    }
+
+    let book_id = target.map(|target| BookStore::read()?.resolve(target)).transpose()?;
+    let path = output.unwrap_or_else(|| match &book_id {
+        Some(book_id) => PathBuf::from(format!("{}.ics", book_id)),
+        None => PathBuf::from("calendar.ics"),
+    });
+    let count = export(book_id.as_deref(), &path)?;
+    println!("已匯出 {} 個發布事件至 {}", count, path.display());
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn count_excludes_skipped_events() {
+        let mut registry = Registry::default();
+        registry.record("111", &[
+            (1, "第一章".to_string(), Some("2030-01-04 20:00".to_string())),
+            (2, "第二章".to_string(), Some("2030-01-04".to_string())),
+        ]);
+        let reservations: Vec<&Reservation> = registry.reservations().iter().collect();
+        let (calendar, count) = build(&reservations, &HashMap::new());
+        assert_eq!(count, 1);
+        assert_eq!(calendar.matches("BEGIN:VEVENT").count(), 1);
+    }
+
+    #[test]
+    fn only_numeric_remote_ids_become_urls() {
+        assert_eq!(chapter_url("1700000000", "123456").as_deref(), Some("https://www.esjzone.cc/forum/1700000000/123456.html"));
+        assert_eq!(chapter_url("1700000000", ""), None);
+        assert_eq!(chapter_url("1700000000", "../123"), None);
+        assert_eq!(chapter_url("1700000000", "123?x=1"), None);
+    }
+}
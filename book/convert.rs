@@ -0,0 +1,142 @@
+use anyhow::{anyhow, Context, Result};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// OpenCC 文字詞典目錄，依序套用：簡轉繁 → 台灣慣用詞 → 台灣異體字
+// 詞典不隨本專案發布，需自行從 OpenCC 的 data/dictionary 下載 (見 README)
+pub const DICTIONARY_DIR: &str = "opencc";
+const STAGES: [&[&str]; 3] = [
+    &["STPhrases.txt", "STCharacters.txt"],
+    &["TWPhrases.txt", "TWPhrasesIT.txt", "TWPhrasesName.txt", "TWPhrasesOther.txt"],
+    &["TWVariants.txt"],
+];
+
+struct Dictionary {
+    map: HashMap<String, String>,
+    max_len: usize,
+}
+
+impl Dictionary {
+    fn new() -> Self {
+        Self { map: HashMap::new(), max_len: 0 }
+    }
+
+    fn load(&mut self, path: &Path) -> Result<()> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("讀取詞典 {} 失敗", path.display()))?;
+        for line in content.lines() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            // 格式：原詞<Tab>候選1 候選2 ...，取第一個候選
+            let mut parts = line.split_whitespace();
+            if let (Some(key), Some(value)) = (parts.next(), parts.next()) {
+                self.max_len = self.max_len.max(key.chars().count());
+                self.map.insert(key.to_string(), value.to_string());
+            }
+        }
+        Ok(())
+    }
+
+    // 正向最大匹配，回傳 (起始位元組, 結束位元組, 替換文字)
+    fn matches<'a>(&'a self, text: &str) -> Vec<(usize, usize, &'a str)> {
+        let bounds: Vec<usize> = text.char_indices()
+            .map(|(i, _)| i)
+            .chain(std::iter::once(text.len()))
+            .collect();
+        let char_count = bounds.len() - 1;
+        let mut found = Vec::new();
+        let mut i = 0;
+        while i < char_count {
+            let longest = self.max_len.min(char_count - i);
+            let hit = (1..=longest).rev().find_map(|len| {
+                self.map.get(&text[bounds[i]..bounds[i + len]]).map(|value| (len, value))
+            });
+            match hit {
+                Some((len, value)) => {
+                    found.push((bounds[i], bounds[i + len], value.as_str()));
+                    i += len;
+                }
+                None => i += 1,
+            }
+        }
+        found
+    }
+
+    fn convert(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, value) in self.matches(text) {
+            output.push_str(&text[last..start]);
+            output.push_str(value);
+            last = end;
+        }
+        output.push_str(&text[last..]);
+        output
+    }
+}
+
+// 每個階段至少需要一個詞典文件
+fn stage_paths(files: &[&str]) -> Result<Vec<PathBuf>> {
+    let paths: Vec<PathBuf> = files.iter()
+        .map(|file| Path::new(DICTIONARY_DIR).join(file))
+        .filter(|path| path.exists())
+        .collect();
+    if paths.is_empty() {
+        return Err(anyhow!("找不到 OpenCC 詞典文件: {}/{}，請從 OpenCC 的 data/dictionary 下載詞典放到 {} 目錄",
+                           DICTIONARY_DIR, files[0], DICTIONARY_DIR));
+    }
+    Ok(paths)
+}
+
+// 登記或修改書籍時檢查詞典是否齊全，不實際載入
+pub fn check_dictionaries() -> Result<()> {
+    for files in STAGES {
+        stage_paths(files)?;
+    }
+    Ok(())
+}
+
+pub struct Converter {
+    stages: Vec<Dictionary>,
+    custom: Dictionary,
+}
+
+impl Converter {
+    pub fn build(custom_dict: Option<&Path>) -> Result<Self> {
+        let mut stages = Vec::new();
+        for files in STAGES {
+            let mut dictionary = Dictionary::new();
+            for path in stage_paths(files)? {
+                dictionary.load(&path)?;
+            }
+            stages.push(dictionary);
+        }
+
+        let mut custom = Dictionary::new();
+        if let Some(path) = custom_dict {
+            custom.load(path)?;
+        }
+        Ok(Self { stages, custom })
+    }
+
+    // 自訂詞典優先匹配，命中的詞組原樣輸出，不再經過後續轉換
+    pub fn convert(&self, text: &str) -> String {
+        let mut output = String::with_capacity(text.len());
+        let mut last = 0;
+        for (start, end, value) in self.custom.matches(text) {
+            output.push_str(&self.convert_segment(&text[last..start]));
+            output.push_str(value);
+            last = end;
+        }
+        output.push_str(&self.convert_segment(&text[last..]));
+        output
+    }
+
+    fn convert_segment(&self, segment: &str) -> String {
+        self.stages.iter()
+            .fold(segment.to_string(), |text, dictionary| dictionary.convert(&text))
+    }
+}
@@ -0,0 +1,237 @@
+use crate::book::matching::normalize_title;
+use crate::book::{Ledger, RawChapter};
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::collections::HashMap;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum DiffKind {
+    // 本地有、遠端沒有
+    MissingRemote,
+    // 遠端有、本地沒有
+    MissingLocal,
+    TitleMismatch,
+    OrderMismatch,
+    DuplicateRemote,
+    // 章節列表記錄的標題與稿件不同
+    LedgerMismatch,
+}
+
+#[derive(Serialize)]
+pub struct DiffItem {
+    kind: DiffKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    index: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    local_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    remote_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct DiffReport {
+    local_count: usize,
+    remote_count: usize,
+    items: Vec<DiffItem>,
+}
+
+impl DiffReport {
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).context("序列化失敗")
+    }
+
+    pub fn print(&self) {
+        println!("本地 {} 章，遠端 {} 章", self.local_count, self.remote_count);
+        if self.items.is_empty() {
+            println!("本地與遠端目錄一致");
+            return;
+        }
+        println!("{:<10}  {:>6}  {:<30}  遠端標題", "類型", "章節", "本地標題");
+        for item in &self.items {
+            let index = item.index.map_or("-".to_string(), |index| index.to_string());
+            let remote = match (&item.remote_title, &item.remote_id) {
+                (Some(title), Some(id)) => format!("{} ({})", title, id),
+                (Some(title), None) => title.clone(),
+                _ => "-".to_string(),
+            };
+            println!("{:<10}  {:>6}  {:<30}  {}",
+                     kind_label(item.kind), index, item.local_title.as_deref().unwrap_or("-"), remote);
+        }
+        for kind in [DiffKind::MissingRemote, DiffKind::MissingLocal, DiffKind::TitleMismatch,
+                     DiffKind::OrderMismatch, DiffKind::DuplicateRemote, DiffKind::LedgerMismatch] {
+            let count = self.items.iter().filter(|item| item.kind == kind).count();
+            if count > 0 {
+                println!("{}: {}", kind_label(kind), count);
+            }
+        }
+    }
+}
+
+fn kind_label(kind: DiffKind) -> &'static str {
+    match kind {
+        DiffKind::MissingRemote => "遠端缺少",
+        DiffKind::MissingLocal => "本地缺少",
+        DiffKind::TitleMismatch => "標題不符",
+        DiffKind::OrderMismatch => "順序不符",
+        DiffKind::DuplicateRemote => "遠端重複",
+        DiffKind::LedgerMismatch => "列表過期",
+    }
+}
+
+// remote 為遠端目錄依網站順序排列的 (標題, 章節 ID)
+pub fn diff(remote: &[(String, String)], chapters: &[RawChapter], ledger: &Ledger) -> DiffReport {
+    let mut items = Vec::new();
+    let local_titles: Vec<String> = chapters.iter().map(|chapter| normalize_title(&chapter.title)).collect();
+    let local_title = |index: usize| chapters.get(index - 1).map(|chapter| chapter.title.clone());
+
+    // 先以章節列表記錄的 ID 對應，再以正規化後的標題對應
+    let mut matched: Vec<(usize, &str, &str)> = Vec::new();
+    let mut seen_titles: HashMap<String, usize> = HashMap::new();
+    for (title, id) in remote {
+        let normalized = normalize_title(title);
+        let count = seen_titles.entry(normalized.clone()).or_insert(0);
+        *count += 1;
+        let index = ledger.entries()
+            .iter()
+            .find(|entry| entry.remote_id() == Some(id.as_str()))
+            .map(|entry| entry.index())
+            .filter(|&index| (1..=chapters.len()).contains(&index))
+            .or_else(|| local_titles.iter().position(|local| *local == normalized).map(|idx| idx + 1));
+
+        let already_matched = index.is_some_and(|index| matched.iter().any(|&(i, _, _)| i == index));
+        if *count > 1 || already_matched {
+            items.push(DiffItem {
+                kind: DiffKind::DuplicateRemote,
+                index,
+                local_title: index.and_then(local_title),
+                remote_title: Some(title.clone()),
+                remote_id: Some(id.clone()),
+            });
+            continue;
+        }
+        match index {
+            Some(index) => matched.push((index, title, id)),
+            None => items.push(DiffItem {
+                kind: DiffKind::MissingLocal,
+                index: None,
+                local_title: None,
+                remote_title: Some(title.clone()),
+                remote_id: Some(id.clone()),
+            }),
+        }
+    }
+
+    let mut furthest = 0;
+    for &(index, title, id) in &matched {
+        let item = |kind| DiffItem {
+            kind,
+            index: Some(index),
+            local_title: local_title(index),
+            remote_title: Some(title.to_string()),
+            remote_id: Some(id.to_string()),
+        };
+        if normalize_title(title) != local_titles[index - 1] {
+            items.push(item(DiffKind::TitleMismatch));
+        }
+        if index < furthest {
+            items.push(item(DiffKind::OrderMismatch));
+        }
+        furthest = furthest.max(index);
+    }
+
+    for (idx, chapter) in chapters.iter().enumerate() {
+        let index = idx + 1;
+        if !matched.iter().any(|&(i, _, _)| i == index) {
+            items.push(DiffItem {
+                kind: DiffKind::MissingRemote,
+                index: Some(index),
+                local_title: Some(chapter.title.clone()),
+                remote_title: None,
+                remote_id: None,
+            });
+        }
+        if let Some(entry) = ledger.get(index) {
+            if normalize_title(entry.title()) != local_titles[idx] {
+                items.push(DiffItem {
+                    kind: DiffKind::LedgerMismatch,
+                    index: Some(index),
+                    local_title: Some(chapter.title.clone()),
+                    remote_title: Some(entry.title().to_string()),
+                    remote_id: entry.remote_id().map(str::to_string),
+                });
+            }
+        }
+    }
+
+    items.sort_by_key(|item| (item.index.unwrap_or(usize::MAX), item.kind as usize));
+    DiffReport { local_count: chapters.len(), remote_count: remote.len(), items }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapters(titles: &[&str]) -> Vec<RawChapter> {
+        titles.iter()
+            .map(|title| RawChapter {
+                title: title.to_string(),
+                content: String::new(),
+                volume: None,
+                schedule: None,
+                line: None,
+            })
+            .collect()
+    }
+
+    fn remote(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(title, id)| (title.to_string(), id.to_string())).collect()
+    }
+
+    fn kinds(report: &DiffReport) -> Vec<(DiffKind, Option<usize>)> {
+        report.items.iter().map(|item| (item.kind, item.index)).collect()
+    }
+
+    #[test]
+    fn matching_directories_have_no_items() {
+        let local = chapters(&["第一章 開始", "第二章 相遇"]);
+        let report = diff(&remote(&[("第一章 開始", "101"), ("第二章 相遇", "102")]), &local, &Ledger::default());
+        assert!(report.is_empty());
+        assert_eq!((report.local_count, report.remote_count), (2, 2));
+    }
+
+    #[test]
+    fn reports_every_kind_of_difference() {
+        let local = chapters(&["第一章 開始", "第二章 相遇", "第三章 離別", "第四章 重逢"]);
+        let mut ledger = Ledger::default();
+        ledger.upsert(1, "第一章 開始").set_remote_id("101");
+        // 以章節列表的 ID 對應，即使遠端標題已不同
+        ledger.upsert(2, "第二章 相遇").set_remote_id("102");
+        ledger.upsert(4, "第四章 舊標題");
+        let remote = remote(&[
+            ("第一章 開始", "101"),
+            ("第三章 離別", "103"),
+            ("第二章 相遇（修訂）", "102"),
+            ("第三章 離別", "105"),
+            ("番外 一", "200"),
+        ]);
+        let report = diff(&remote, &local, &ledger);
+        assert_eq!(kinds(&report), [
+            (DiffKind::TitleMismatch, Some(2)),
+            (DiffKind::OrderMismatch, Some(2)),
+            (DiffKind::DuplicateRemote, Some(3)),
+            (DiffKind::MissingRemote, Some(4)),
+            (DiffKind::LedgerMismatch, Some(4)),
+            (DiffKind::MissingLocal, None),
+        ]);
+        let json = report.to_json().unwrap();
+        assert!(json.contains("\"kind\": \"missing_local\""));
+        assert!(json.contains("\"remote_id\": \"200\""));
+    }
+}
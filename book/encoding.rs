@@ -0,0 +1,43 @@
+use anyhow::{anyhow, Context, Result};
+use chardetng::EncodingDetector;
+use encoding_rs::{Encoding, UTF_8};
+use std::fs;
+
+pub fn read_text(path: &str, label: Option<&str>) -> Result<String> {
+    let bytes = fs::read(path).context("無法打開文件")?;
+    decode(&bytes, label)
+}
+
+fn decode(bytes: &[u8], label: Option<&str>) -> Result<String> {
+    let encoding = match label.map(str::trim).filter(|label| !label.is_empty()) {
+        Some(label) => Encoding::for_label(label.as_bytes())
+            .ok_or_else(|| anyhow!("不支援的文件編碼: {}", label))?,
+        None => detect(bytes),
+    };
+
+    // 指定或檢測到的編碼若與 BOM 相符則一併去除 BOM
+    let (text, had_errors) = encoding.decode_with_bom_removal(bytes);
+    if had_errors {
+        return Err(anyhow!(
+            "文件無法以 {} 編碼正確解碼，請在 book.json 中設定 encoding 欄位",
+            encoding.name()
+        ));
+    }
+    if encoding != UTF_8 {
+        println!("文件編碼: {}", encoding.name());
+    }
+    Ok(text.into_owned())
+}
+
+fn detect(bytes: &[u8]) -> &'static Encoding {
+    // BOM (UTF-8 / UTF-16LE / UTF-16BE) 優先
+    if let Some((encoding, _)) = Encoding::for_bom(bytes) {
+        return encoding;
+    }
+    if std::str::from_utf8(bytes).is_ok() {
+        return UTF_8;
+    }
+    let mut detector = EncodingDetector::new();
+    detector.feed(bytes, true);
+    detector.guess(None, true)
+}
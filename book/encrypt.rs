@@ -0,0 +1,82 @@
+use crate::book::font::NativeEncryptor;
+use crate::book::EXECUTABLE_FILE;
+use crate::utils::{get_executable_name, load_optional};
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::Arc;
+
+// 章節加密：將原文轉為替換字元後的文本 (target)，並產生對應的字型 (font)
+pub trait Encryptor: Send + Sync {
+    fn encrypt(&self, source: &Path, target: &Path, font: &Path) -> Result<()>;
+}
+
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    // 呼叫 encryptor/encryptor 外部程式
+    #[default]
+    External,
+    // 內建實作，不需要額外的執行檔
+    Native,
+}
+
+// config.toml 的 [encrypt] 區段
+#[derive(Deserialize, Default)]
+#[serde(default)]
+pub struct EncryptSettings {
+    backend: Backend,
+    // 外部程式路徑，預設為 encryptor/encryptor
+    executable: Option<String>,
+    // 內建加密使用的來源字型，需為 TrueType (glyf) 字型
+    font: Option<PathBuf>,
+    // 同時加密的章節數，預設為 CPU 核心數
+    concurrency: Option<usize>,
+}
+
+impl EncryptSettings {
+    pub fn load() -> Result<Self> {
+        Ok(load_optional("encrypt")?.unwrap_or_default())
+    }
+
+    pub fn concurrency(&self) -> usize {
+        self.concurrency
+            .unwrap_or_else(|| std::thread::available_parallelism().map_or(4, |count| count.get()))
+            .max(1)
+    }
+
+    pub fn encryptor(&self) -> Result<Arc<dyn Encryptor>> {
+        match self.backend {
+            Backend::External => {
+                let executable = self.executable.clone()
+                    .unwrap_or_else(|| get_executable_name(EXECUTABLE_FILE));
+                Ok(Arc::new(ExternalEncryptor { executable }))
+            }
+            Backend::Native => {
+                let font = self.font.as_ref()
+                    .ok_or_else(|| anyhow!("使用內建加密時需在 config.toml 的 [encrypt] 設定 font"))?;
+                Ok(Arc::new(NativeEncryptor::load(font)?))
+            }
+        }
+    }
+}
+
+pub struct ExternalEncryptor {
+    executable: String,
+}
+
+impl Encryptor for ExternalEncryptor {
+    fn encrypt(&self, source: &Path, target: &Path, font: &Path) -> Result<()> {
+        let output = Command::new(&self.executable)
+            .arg("-f").arg(source)
+            .arg("-s").arg(target)
+            .arg("-t").arg(font)
+            .output()
+            .with_context(|| format!("執行加密程序 {} 失敗", self.executable))?;
+        if !output.status.success() {
+            return Err(anyhow!("加密失敗: {}", String::from_utf8_lossy(&output.stderr)));
+        }
+        Ok(())
+    }
+}
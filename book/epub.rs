@@ -0,0 +1,296 @@
+use crate::book::RawChapter;
+use anyhow::{anyhow, Context, Result};
+use roxmltree::{Document, Node, ParsingOptions};
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use zip::ZipArchive;
+
+// XHTML 常見但 XML 未定義的實體，解析前先換成數字實體
+const HTML_ENTITIES: [(&str, &str); 10] = [
+    ("&nbsp;", "&#160;"),
+    ("&ensp;", "&#8194;"),
+    ("&emsp;", "&#8195;"),
+    ("&hellip;", "&#8230;"),
+    ("&mdash;", "&#8212;"),
+    ("&ndash;", "&#8211;"),
+    ("&ldquo;", "&#8220;"),
+    ("&rdquo;", "&#8221;"),
+    ("&lsquo;", "&#8216;"),
+    ("&rsquo;", "&#8217;"),
+];
+
+const BLOCK_TAGS: [&str; 18] = [
+    "p", "div", "h1", "h2", "h3", "h4", "h5", "h6", "li", "blockquote",
+    "section", "article", "tr", "pre", "figure", "hr", "dt", "dd",
+];
+const SKIP_TAGS: [&str; 6] = ["head", "script", "style", "rt", "rp", "nav"];
+
+pub fn is_epub(book_path: &str) -> bool {
+    Path::new(book_path)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("epub"))
+}
+
+struct Epub {
+    archive: ZipArchive<File>,
+}
+
+impl Epub {
+    fn open(book_path: &str) -> Result<Self> {
+        let file = File::open(book_path).context("無法打開文件")?;
+        let archive = ZipArchive::new(file).context("無法解析 EPUB 文件")?;
+        Ok(Self { archive })
+    }
+
+    fn read(&mut self, name: &str) -> Result<String> {
+        let mut entry = self.archive.by_name(name)
+            .with_context(|| format!("EPUB 中缺少文件: {}", name))?;
+        let mut content = String::new();
+        entry.read_to_string(&mut content)
+            .with_context(|| format!("讀取 EPUB 文件 {} 失敗", name))?;
+        Ok(content)
+    }
+}
+
+fn parse_xml(content: &str) -> Result<Document<'_>> {
+    let options = ParsingOptions { allow_dtd: true, ..ParsingOptions::default() };
+    Document::parse_with_options(content, options).context("XML 解析失敗")
+}
+
+fn replace_entities(content: &str) -> String {
+    HTML_ENTITIES.iter()
+        .fold(content.to_string(), |text, (from, to)| text.replace(from, to))
+}
+
+fn parent_dir(path: &str) -> &str {
+    path.rfind('/').map_or("", |i| &path[..=i])
+}
+
+fn percent_decode(href: &str) -> String {
+    let bytes = href.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = (bytes[i] == b'%' && i + 2 < bytes.len())
+            .then(|| std::str::from_utf8(&bytes[i + 1..i + 3]).ok())
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match hex {
+            Some(byte) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            None => {
+                decoded.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+// 將相對於 base 目錄的 href 轉為壓縮檔內的完整路徑 (去除 #片段)
+fn resolve_href(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let mut parts: Vec<String> = Vec::new();
+    for part in format!("{}{}", base, percent_decode(href)).split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part.to_string()),
+        }
+    }
+    parts.join("/")
+}
+
+fn element_text(node: Node) -> String {
+    node.descendants()
+        .filter(|n| n.is_text())
+        .filter_map(|n| n.text())
+        .collect::<String>()
+        .split_ascii_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn find_rootfile(epub: &mut Epub) -> Result<String> {
+    let container = epub.read("META-INF/container.xml")?;
+    let doc = parse_xml(&container)?;
+    doc.descendants()
+        .find(|n| n.has_tag_name("rootfile"))
+        .and_then(|n| n.attribute("full-path"))
+        .map(str::to_string)
+        .ok_or_else(|| anyhow!("EPUB 中找不到 OPF 文件"))
+}
+
+struct Package {
+    // 依 spine 順序排列的內容文件
+    spine: Vec<String>,
+    nav: Option<String>,
+    ncx: Option<String>,
+}
+
+fn read_package(epub: &mut Epub, opf_path: &str) -> Result<Package> {
+    let content = epub.read(opf_path)?;
+    let doc = parse_xml(&content)?;
+    let base = parent_dir(opf_path);
+
+    let mut manifest = HashMap::new();
+    let mut nav = None;
+    for item in doc.descendants().filter(|n| n.has_tag_name("item")) {
+        let (Some(id), Some(href)) = (item.attribute("id"), item.attribute("href")) else {
+            continue;
+        };
+        let path = resolve_href(base, href);
+        if item.attribute("properties").is_some_and(|p| p.split_whitespace().any(|p| p == "nav")) {
+            nav = Some(path.clone());
+        }
+        manifest.insert(id, path);
+    }
+
+    let spine_node = doc.descendants()
+        .find(|n| n.has_tag_name("spine"))
+        .ok_or_else(|| anyhow!("OPF 中找不到 spine"))?;
+    let ncx = spine_node.attribute("toc").and_then(|id| manifest.get(id)).cloned();
+    let spine = spine_node.children()
+        .filter(|n| n.has_tag_name("itemref"))
+        .filter(|n| n.attribute("linear") != Some("no"))
+        .filter_map(|n| n.attribute("idref"))
+        .filter_map(|id| manifest.get(id).cloned())
+        .collect();
+
+    Ok(Package { spine, nav, ncx })
+}
+
+// 由 EPUB3 nav 或 EPUB2 NCX 取得 文件路徑 -> 目錄標題
+fn read_toc(epub: &mut Epub, package: &Package) -> Result<HashMap<String, String>> {
+    let mut titles = HashMap::new();
+    if let Some(nav_path) = &package.nav {
+        let content = replace_entities(&epub.read(nav_path)?);
+        let doc = parse_xml(&content)?;
+        let base = parent_dir(nav_path);
+        let toc = doc.descendants()
+            .filter(|n| n.has_tag_name("nav"))
+            .find(|n| n.attributes().any(|a| a.name() == "type" && a.value() == "toc"))
+            .or_else(|| doc.descendants().find(|n| n.has_tag_name("nav")));
+        if let Some(toc) = toc {
+            for link in toc.descendants().filter(|n| n.has_tag_name("a")) {
+                if let Some(href) = link.attribute("href") {
+                    titles.entry(resolve_href(base, href)).or_insert_with(|| element_text(link));
+                }
+            }
+        }
+    } else if let Some(ncx_path) = &package.ncx {
+        let content = epub.read(ncx_path)?;
+        let doc = parse_xml(&content)?;
+        let base = parent_dir(ncx_path);
+        for point in doc.descendants().filter(|n| n.has_tag_name("navPoint")) {
+            let label = point.children()
+                .find(|n| n.has_tag_name("navLabel"))
+                .map(element_text);
+            let src = point.children()
+                .find(|n| n.has_tag_name("content"))
+                .and_then(|n| n.attribute("src"));
+            if let (Some(label), Some(src)) = (label, src) {
+                titles.entry(resolve_href(base, src)).or_insert(label);
+            }
+        }
+    }
+    titles.retain(|_, title| !title.is_empty());
+    Ok(titles)
+}
+
+fn flush_line(lines: &mut Vec<String>, current: &mut String) {
+    let line = current.split_ascii_whitespace().collect::<Vec<_>>().join(" ");
+    let line = line.trim();
+    if !line.is_empty() {
+        lines.push(line.to_string());
+    }
+    current.clear();
+}
+
+fn collect_text(node: Node, lines: &mut Vec<String>, current: &mut String) {
+    for child in node.children() {
+        if child.is_text() {
+            current.push_str(child.text().unwrap_or_default());
+            continue;
+        }
+        if !child.is_element() {
+            continue;
+        }
+        let tag = child.tag_name().name().to_ascii_lowercase();
+        if SKIP_TAGS.contains(&tag.as_str()) {
+            continue;
+        }
+        if tag == "br" {
+            flush_line(lines, current);
+        } else if BLOCK_TAGS.contains(&tag.as_str()) {
+            flush_line(lines, current);
+            collect_text(child, lines, current);
+            flush_line(lines, current);
+        } else {
+            collect_text(child, lines, current);
+        }
+    }
+}
+
+// 回傳 (首個標題文字, 正文各行)
+fn document_text(content: &str) -> Result<(Option<String>, Vec<String>)> {
+    let content = replace_entities(content);
+    let doc = parse_xml(&content)?;
+    let body = doc.descendants()
+        .find(|n| n.has_tag_name("body"))
+        .unwrap_or_else(|| doc.root_element());
+    let heading = body.descendants()
+        .find(|n| matches!(n.tag_name().name(), "h1" | "h2" | "h3"))
+        .map(element_text)
+        .filter(|text| !text.is_empty());
+
+    let mut lines = Vec::new();
+    let mut current = String::new();
+    collect_text(body, &mut lines, &mut current);
+    flush_line(&mut lines, &mut current);
+    Ok((heading, lines))
+}
+
+pub fn read_chapters(book_path: &str) -> Result<Vec<RawChapter>> {
+    let mut epub = Epub::open(book_path)?;
+    let opf_path = find_rootfile(&mut epub)?;
+    let package = read_package(&mut epub, &opf_path)?;
+    let titles = read_toc(&mut epub, &package)?;
+
+    let mut chapters = Vec::new();
+    for path in &package.spine {
+        let content = epub.read(path)?;
+        let (heading, mut lines) = document_text(&content)
+            .with_context(|| format!("解析 {} 失敗", path))?;
+        // 僅有圖片的頁面 (封面、插圖等) 不計入章節
+        if lines.is_empty() {
+            continue;
+        }
+        let title = match titles.get(path).cloned().or(heading) {
+            Some(title) => title,
+            None => Path::new(path).file_stem()
+                .map(|stem| stem.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        // 正文開頭與標題相同的行已由標題表示，不重複輸出
+        if lines.first().is_some_and(|line| *line == title) {
+            lines.remove(0);
+        }
+        let mut text = lines.join("\n");
+        text.push('\n');
+        chapters.push(RawChapter {
+            title,
+            content: text,
+            volume: None,
+            schedule: None,
+            line: None,
+        });
+    }
+    Ok(chapters)
+}
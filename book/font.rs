@@ -0,0 +1,482 @@
+use crate::book::encrypt::Encryptor;
+use anyhow::{anyhow, Context, Result};
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use sha2::{Digest, Sha256};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+use ttf_parser::{Face, GlyphId, Tag};
+
+// 私用區 U+E000..U+F8FF，每個章節最多替換 6400 個不同的字
+const PUA_START: u32 = 0xE000;
+const PUA_SIZE: usize = 6400;
+// 與字形編號無關的提示 (hinting) 表，原樣複製
+const HINTING_TABLES: [&[u8; 4]; 4] = [b"cvt ", b"fpgm", b"prep", b"gasp"];
+const CHECKSUM_MAGIC: u32 = 0xB1B0AFBA;
+
+// 複合字形的旗標
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+type Table = ([u8; 4], Vec<u8>);
+
+// 內建加密：將章節中的漢字隨機對應到私用區字元，並從來源字型截取對應字形產生 WOFF
+pub struct NativeEncryptor {
+    data: Vec<u8>,
+}
+
+impl NativeEncryptor {
+    pub fn load(path: &Path) -> Result<Self> {
+        let data = fs::read(path).with_context(|| format!("讀取字型 {} 失敗", path.display()))?;
+        let face = Face::parse(&data, 0)
+            .map_err(|e| anyhow!("字型 {} 解析失敗: {}", path.display(), e))?;
+        let raw = face.raw_face();
+        if raw.table(Tag::from_bytes(b"glyf")).is_none() || raw.table(Tag::from_bytes(b"loca")).is_none() {
+            return Err(anyhow!("字型 {} 不是 TrueType (glyf) 字型，內建加密不支援 CFF 字型", path.display()));
+        }
+        Ok(Self { data })
+    }
+}
+
+impl Encryptor for NativeEncryptor {
+    fn encrypt(&self, source: &Path, target: &Path, font: &Path) -> Result<()> {
+        let face = Face::parse(&self.data, 0).map_err(|e| anyhow!("字型解析失敗: {}", e))?;
+        let content = fs::read_to_string(source).context("讀取章節文件失敗")?;
+
+        let mapping = remap(&face, &content);
+        let codes: HashMap<char, char> = mapping.iter()
+            .enumerate()
+            .filter_map(|(i, (c, _))| char::from_u32(PUA_START + i as u32).map(|code| (*c, code)))
+            .collect();
+        let text: String = content.chars().map(|c| codes.get(&c).copied().unwrap_or(c)).collect();
+        let glyphs: Vec<GlyphId> = mapping.iter().map(|(_, glyph)| *glyph).collect();
+        let woff = build_woff(subset(&face, &glyphs)?)?;
+
+        write(target, text.as_bytes())?;
+        write(font, &woff)
+    }
+}
+
+fn write(path: &Path, content: &[u8]) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("無法創建目錄 {}", parent.display()))?;
+    }
+    fs::write(path, content).with_context(|| format!("寫入 {} 失敗", path.display()))
+}
+
+fn is_cjk(c: char) -> bool {
+    matches!(c as u32, 0x3400..=0x4DBF | 0x4E00..=0x9FFF | 0xF900..=0xFAFF | 0x20000..=0x2FA1F)
+}
+
+// 章節中字型有收錄的漢字，以隨機順序對應到私用區，每次加密的對應都不同
+fn remap(face: &Face, content: &str) -> Vec<(char, GlyphId)> {
+    let mut seen = HashSet::new();
+    let mut mapping: Vec<(char, GlyphId)> = content.chars()
+        .filter(|c| is_cjk(*c) && seen.insert(*c))
+        .filter_map(|c| face.glyph_index(c).map(|glyph| (c, glyph)))
+        .take(PUA_SIZE)
+        .collect();
+
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos());
+    let digest = Sha256::new()
+        .chain_update(content.as_bytes())
+        .chain_update(nanos.to_be_bytes())
+        .finalize();
+    let mut state = u64::from_be_bytes(digest[..8].try_into().unwrap_or_default()) | 1;
+    for i in (1..mapping.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+        mapping.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+    mapping
+}
+
+fn read_u16(data: &[u8], offset: usize) -> Result<u16> {
+    data.get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| anyhow!("字型資料損毀"))
+}
+
+fn read_u32(data: &[u8], offset: usize) -> Result<u32> {
+    data.get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| anyhow!("字型資料損毀"))
+}
+
+fn write_u16(data: &mut [u8], offset: usize, value: u16) -> Result<()> {
+    data.get_mut(offset..offset + 2)
+        .ok_or_else(|| anyhow!("字型資料損毀"))?
+        .copy_from_slice(&value.to_be_bytes());
+    Ok(())
+}
+
+fn pad(data: &mut Vec<u8>) {
+    data.resize(data.len().div_ceil(4) * 4, 0);
+}
+
+// 複合字形中各元件字形編號的位置，簡單字形回傳空列表
+fn component_offsets(glyph: &[u8]) -> Result<Vec<usize>> {
+    if glyph.len() < 10 || (read_u16(glyph, 0)? as i16) >= 0 {
+        return Ok(Vec::new());
+    }
+    let mut offsets = Vec::new();
+    let mut pos = 10;
+    loop {
+        let flags = read_u16(glyph, pos)?;
+        read_u16(glyph, pos + 2)?;
+        offsets.push(pos + 2);
+        pos += if flags & ARG_1_AND_2_ARE_WORDS != 0 { 8 } else { 6 };
+        pos += if flags & WE_HAVE_A_SCALE != 0 {
+            2
+        } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+            4
+        } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+            8
+        } else {
+            0
+        };
+        if flags & MORE_COMPONENTS == 0 {
+            return Ok(offsets);
+        }
+    }
+}
+
+fn cmap(count: usize) -> Vec<u8> {
+    // 私用區字元依序對應字形 1..=count，可用單一區段加上 idDelta 表示
+    let mut segments = vec![(0xFFFFu16, 0xFFFFu16, 1u16)];
+    if count > 0 {
+        let start = PUA_START as u16;
+        segments.insert(0, (start, start + count as u16 - 1, 1u16.wrapping_sub(start)));
+    }
+    let seg_count = segments.len() as u16;
+    let search_range = 2 * (1u16 << (15 - seg_count.leading_zeros()));
+    let mut subtable = Vec::new();
+    for value in [4, 16 + 8 * seg_count, 0, seg_count * 2, search_range,
+                  search_range.trailing_zeros() as u16 - 1, seg_count * 2 - search_range] {
+        subtable.extend(value.to_be_bytes());
+    }
+    segments.iter().for_each(|(_, end, _)| subtable.extend(end.to_be_bytes()));
+    subtable.extend(0u16.to_be_bytes());
+    segments.iter().for_each(|(start, _, _)| subtable.extend(start.to_be_bytes()));
+    segments.iter().for_each(|(_, _, delta)| subtable.extend(delta.to_be_bytes()));
+    segments.iter().for_each(|_| subtable.extend(0u16.to_be_bytes()));
+
+    // Unicode BMP 與 Windows BMP 兩個編碼記錄共用同一個子表
+    let mut table = Vec::new();
+    for value in [0u16, 2, 0, 3] {
+        table.extend(value.to_be_bytes());
+    }
+    table.extend(20u32.to_be_bytes());
+    for value in [3u16, 1] {
+        table.extend(value.to_be_bytes());
+    }
+    table.extend(20u32.to_be_bytes());
+    table.extend(subtable);
+    table
+}
+
+// 截取字形 0 (.notdef)、指定字形與其複合元件，字形 i 對應私用區第 i 個字元
+fn subset(face: &Face, glyphs: &[GlyphId]) -> Result<Vec<Table>> {
+    let raw = face.raw_face();
+    let table = |tag: &[u8; 4]| raw.table(Tag::from_bytes(tag));
+    let required = |tag: &[u8; 4]| {
+        table(tag).ok_or_else(|| anyhow!("字型缺少 {} 表", String::from_utf8_lossy(tag)))
+    };
+    let head = required(b"head")?;
+    let glyf = required(b"glyf")?;
+    let loca = required(b"loca")?;
+    let long_loca = read_u16(head, 50)? != 0;
+    let glyph_data = |id: u16| -> Result<&[u8]> {
+        let index = usize::from(id);
+        let (start, end) = if long_loca {
+            (read_u32(loca, index * 4)? as usize, read_u32(loca, index * 4 + 4)? as usize)
+        } else {
+            (read_u16(loca, index * 2)? as usize * 2, read_u16(loca, index * 2 + 2)? as usize * 2)
+        };
+        glyf.get(start..end).ok_or_else(|| anyhow!("字形 {} 資料損毀", id))
+    };
+
+    let mut order: Vec<u16> = std::iter::once(0).chain(glyphs.iter().map(|glyph| glyph.0)).collect();
+    let mut new_ids: HashMap<u16, u16> = HashMap::new();
+    for (new_id, id) in order.iter().enumerate() {
+        new_ids.entry(*id).or_insert(new_id as u16);
+    }
+    let mut next = 0;
+    while next < order.len() {
+        let glyph = glyph_data(order[next])?;
+        for offset in component_offsets(glyph)? {
+            let component = read_u16(glyph, offset)?;
+            if let Entry::Vacant(entry) = new_ids.entry(component) {
+                entry.insert(u16::try_from(order.len()).map_err(|_| anyhow!("字形數量超過上限"))?);
+                order.push(component);
+            }
+        }
+        next += 1;
+    }
+    let num_glyphs = u16::try_from(order.len()).map_err(|_| anyhow!("字形數量超過上限"))?;
+
+    let mut new_glyf = Vec::new();
+    let mut new_loca = Vec::new();
+    let mut hmtx = Vec::new();
+    for id in &order {
+        new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+        let mut glyph = glyph_data(*id)?.to_vec();
+        for offset in component_offsets(&glyph)? {
+            let component = read_u16(&glyph, offset)?;
+            write_u16(&mut glyph, offset, new_ids[&component])?;
+        }
+        new_glyf.extend(glyph);
+        pad(&mut new_glyf);
+        hmtx.extend(face.glyph_hor_advance(GlyphId(*id)).unwrap_or(0).to_be_bytes());
+        hmtx.extend(face.glyph_hor_side_bearing(GlyphId(*id)).unwrap_or(0).to_be_bytes());
+    }
+    new_loca.extend((new_glyf.len() as u32).to_be_bytes());
+
+    let mut new_head = head.to_vec();
+    new_head[8..12].fill(0);
+    write_u16(&mut new_head, 50, 1)?;
+    let mut hhea = required(b"hhea")?.to_vec();
+    write_u16(&mut hhea, 34, num_glyphs)?;
+    let mut maxp = required(b"maxp")?.to_vec();
+    write_u16(&mut maxp, 4, num_glyphs)?;
+    // post 第 3 版不含字形名稱
+    let mut post = table(b"post").map_or(vec![0; 32], |post| post.to_vec());
+    post.resize(32, 0);
+    post[0..4].copy_from_slice(&0x0003_0000u32.to_be_bytes());
+
+    let mut tables = vec![
+        (*b"cmap", cmap(glyphs.len())),
+        (*b"glyf", new_glyf),
+        (*b"head", new_head),
+        (*b"hhea", hhea),
+        (*b"hmtx", hmtx),
+        (*b"loca", new_loca),
+        (*b"maxp", maxp),
+        (*b"post", post),
+    ];
+    if let Some(os2) = table(b"OS/2") {
+        let mut os2 = os2.to_vec();
+        if !glyphs.is_empty() && os2.len() >= 68 {
+            write_u16(&mut os2, 64, PUA_START as u16)?;
+            write_u16(&mut os2, 66, (PUA_START as usize + glyphs.len() - 1) as u16)?;
+        }
+        tables.push((*b"OS/2", os2));
+    }
+    for tag in [b"name"].into_iter().chain(HINTING_TABLES) {
+        if let Some(data) = table(tag) {
+            tables.push((*tag, data.to_vec()));
+        }
+    }
+    Ok(tables)
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+// 依 WOFF 1.0 封裝，各表以 zlib 壓縮 (壓縮後較大則保留原始資料)
+fn build_woff(mut tables: Vec<Table>) -> Result<Vec<u8>> {
+    tables.sort_by_key(|(tag, _)| *tag);
+    let num_tables = tables.len() as u16;
+    let checksums: Vec<u32> = tables.iter().map(|(_, data)| checksum(data)).collect();
+
+    // 依 sfnt 的排列計算 head 的 checkSumAdjustment
+    let entry_selector = 15 - num_tables.leading_zeros() as u16;
+    let search_range = 16 * (1u16 << entry_selector);
+    let mut directory = Vec::new();
+    for value in [1u16, 0, num_tables, search_range, entry_selector, num_tables * 16 - search_range] {
+        directory.extend(value.to_be_bytes());
+    }
+    let mut offset = 12 + 16 * tables.len();
+    for ((tag, data), sum) in tables.iter().zip(&checksums) {
+        directory.extend(tag);
+        directory.extend(sum.to_be_bytes());
+        directory.extend((offset as u32).to_be_bytes());
+        directory.extend((data.len() as u32).to_be_bytes());
+        offset += data.len().div_ceil(4) * 4;
+    }
+    let total_sfnt_size = offset as u32;
+    let sum = checksums.iter().fold(checksum(&directory), |sum, value| sum.wrapping_add(*value));
+    if let Some((_, head)) = tables.iter_mut().find(|(tag, _)| tag == b"head") {
+        head[8..12].copy_from_slice(&CHECKSUM_MAGIC.wrapping_sub(sum).to_be_bytes());
+    }
+
+    let mut entries = Vec::new();
+    let mut body = Vec::new();
+    let data_start = 44 + 20 * tables.len();
+    for ((tag, data), sum) in tables.iter().zip(&checksums) {
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::best());
+        encoder.write_all(data).context("壓縮字型失敗")?;
+        let compressed = encoder.finish().context("壓縮字型失敗")?;
+        let stored = if compressed.len() < data.len() { compressed } else { data.clone() };
+        entries.extend(tag);
+        entries.extend(((data_start + body.len()) as u32).to_be_bytes());
+        entries.extend((stored.len() as u32).to_be_bytes());
+        entries.extend((data.len() as u32).to_be_bytes());
+        entries.extend(sum.to_be_bytes());
+        body.extend(stored);
+        pad(&mut body);
+    }
+
+    let mut woff = Vec::with_capacity(data_start + body.len());
+    woff.extend(b"wOFF");
+    woff.extend(0x0001_0000u32.to_be_bytes());
+    woff.extend(((data_start + body.len()) as u32).to_be_bytes());
+    woff.extend(num_tables.to_be_bytes());
+    woff.extend(0u16.to_be_bytes());
+    woff.extend(total_sfnt_size.to_be_bytes());
+    woff.extend(1u16.to_be_bytes());
+    woff.extend(0u16.to_be_bytes());
+    woff.extend([0u8; 20]);
+    woff.extend(entries);
+    woff.extend(body);
+    Ok(woff)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use flate2::read::ZlibDecoder;
+    use std::io::Read;
+
+    // 可用環境變數 NATIVE_ENCRYPT_TEST_FONT 指定測試用的 TrueType 字型
+    const TEST_FONT: &str = "/usr/share/fonts/truetype/dejavu/DejaVuSans.ttf";
+
+    fn test_font() -> Vec<u8> {
+        let path = std::env::var("NATIVE_ENCRYPT_TEST_FONT").unwrap_or_else(|_| TEST_FONT.to_string());
+        fs::read(&path).unwrap_or_else(|e| panic!("讀取測試字型 {} 失敗: {}", path, e))
+    }
+
+    // 將 WOFF 還原為 sfnt，檢查各表長度與整體大小
+    fn unwrap_woff(woff: &[u8]) -> Vec<u8> {
+        assert_eq!(&woff[0..4], b"wOFF");
+        assert_eq!(read_u32(woff, 8).unwrap() as usize, woff.len());
+        let num_tables = read_u16(woff, 12).unwrap() as usize;
+        let mut tables = Vec::new();
+        for i in 0..num_tables {
+            let entry = 44 + 20 * i;
+            let offset = read_u32(woff, entry + 4).unwrap() as usize;
+            let stored = read_u32(woff, entry + 8).unwrap() as usize;
+            let length = read_u32(woff, entry + 12).unwrap() as usize;
+            let raw = &woff[offset..offset + stored];
+            let mut data = Vec::new();
+            if stored < length {
+                ZlibDecoder::new(raw).read_to_end(&mut data).unwrap();
+            } else {
+                data.extend(raw);
+            }
+            assert_eq!(data.len(), length);
+            tables.push((&woff[entry..entry + 4], read_u32(woff, entry + 16).unwrap(), data));
+        }
+
+        let entry_selector = 15 - (num_tables as u16).leading_zeros() as u16;
+        let search_range = 16 * (1u16 << entry_selector);
+        let mut sfnt = Vec::new();
+        for value in [1u16, 0, num_tables as u16, search_range, entry_selector, num_tables as u16 * 16 - search_range] {
+            sfnt.extend(value.to_be_bytes());
+        }
+        let mut offset = 12 + 16 * num_tables;
+        for (tag, sum, data) in &tables {
+            sfnt.extend(*tag);
+            sfnt.extend(sum.to_be_bytes());
+            sfnt.extend((offset as u32).to_be_bytes());
+            sfnt.extend((data.len() as u32).to_be_bytes());
+            offset += data.len().div_ceil(4) * 4;
+        }
+        for (_, _, data) in &tables {
+            sfnt.extend(data);
+            pad(&mut sfnt);
+        }
+        assert_eq!(sfnt.len(), read_u32(woff, 16).unwrap() as usize);
+        sfnt
+    }
+
+    // 記錄字形輪廓的繪製指令，用來比較截取前後的字形
+    #[derive(Default)]
+    struct Outline(Vec<String>);
+
+    impl ttf_parser::OutlineBuilder for Outline {
+        fn move_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("M {} {}", x, y));
+        }
+
+        fn line_to(&mut self, x: f32, y: f32) {
+            self.0.push(format!("L {} {}", x, y));
+        }
+
+        fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+            self.0.push(format!("Q {} {} {} {}", x1, y1, x, y));
+        }
+
+        fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+            self.0.push(format!("C {} {} {} {} {} {}", x1, y1, x2, y2, x, y));
+        }
+
+        fn close(&mut self) {
+            self.0.push("Z".to_string());
+        }
+    }
+
+    fn outline(face: &Face, glyph: GlyphId) -> Vec<String> {
+        let mut outline = Outline::default();
+        face.outline_glyph(glyph, &mut outline);
+        outline.0
+    }
+
+    #[test]
+    fn cmap_maps_private_use_to_glyphs() {
+        let table = cmap(3);
+        let parsed = ttf_parser::cmap::Table::parse(&table).unwrap();
+        let subtable = parsed.subtables.get(0).unwrap();
+        for i in 0..3 {
+            assert_eq!(subtable.glyph_index(PUA_START + i), Some(GlyphId(i as u16 + 1)));
+        }
+        assert_eq!(subtable.glyph_index(PUA_START + 3), None);
+        assert_eq!(subtable.glyph_index('A' as u32), None);
+
+        let table = cmap(0);
+        let parsed = ttf_parser::cmap::Table::parse(&table).unwrap();
+        assert_eq!(parsed.subtables.get(0).unwrap().glyph_index(PUA_START), None);
+    }
+
+    // 需要含複合字形的 TrueType 字型，以 cargo test -- --ignored 執行
+    #[test]
+    #[ignore = "needs font"]
+    fn subset_woff_round_trip() {
+        let data = test_font();
+        let face = Face::parse(&data, 0).unwrap();
+        // Å、é 在常見字型中是複合字形，元件需一併截取
+        let chars = ['A', 'g', 'Å', 'é'];
+        let glyphs: Vec<GlyphId> = chars.iter().map(|c| face.glyph_index(*c).unwrap()).collect();
+        let sfnt = unwrap_woff(&build_woff(subset(&face, &glyphs).unwrap()).unwrap());
+        assert_eq!(checksum(&sfnt), CHECKSUM_MAGIC);
+
+        let output = Face::parse(&sfnt, 0).unwrap();
+        assert!(output.number_of_glyphs() as usize > chars.len() + 1);
+        assert_eq!(output.glyph_index('A'), None);
+        for (i, (c, original)) in chars.iter().zip(&glyphs).enumerate() {
+            let code = char::from_u32(PUA_START + i as u32).unwrap();
+            let glyph = output.glyph_index(code).unwrap();
+            assert_eq!(glyph, GlyphId(i as u16 + 1));
+            assert_eq!(outline(&output, glyph), outline(&face, *original), "{}", c);
+            assert_eq!(output.glyph_hor_advance(glyph), face.glyph_hor_advance(*original));
+        }
+
+        // 沒有任何替換字時仍產生只含 .notdef 的有效字型
+        let sfnt = unwrap_woff(&build_woff(subset(&face, &[]).unwrap()).unwrap());
+        assert_eq!(Face::parse(&sfnt, 0).unwrap().number_of_glyphs(), 1);
+    }
+}
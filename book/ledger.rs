@@ -0,0 +1,228 @@
+use crate::book::schedule::ScheduleState;
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::Path;
+
+const LEDGER_VERSION: u32 = 1;
+
+#[derive(Serialize, Deserialize, Clone, Default)]
+pub struct LedgerEntry {
+    index: usize,
+    title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    volume: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote_id: Option<String>,
+    // RFC 3339 (UTC)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    published_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    updated_at: Option<String>,
+    // 網站時區的預約時間 YYYY-MM-DD HH:MM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    scheduled_at: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    content_hash: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    font_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    css_url: Option<String>,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Ledger {
+    version: u32,
+    chapters: Vec<LedgerEntry>,
+    // 上次預約的最後時段與排程規則，供下次接續排程
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    schedule: Option<ScheduleState>,
+}
+
+impl Default for Ledger {
+    fn default() -> Self {
+        Self { version: LEDGER_VERSION, chapters: Vec::new(), schedule: None }
+    }
+}
+
+impl LedgerEntry {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn volume(&self) -> Option<&str> {
+        self.volume.as_deref()
+    }
+
+    pub fn remote_id(&self) -> Option<&str> {
+        self.remote_id.as_deref()
+    }
+
+    pub fn published_at(&self) -> Option<&str> {
+        self.published_at.as_deref()
+    }
+
+    pub fn updated_at(&self) -> Option<&str> {
+        self.updated_at.as_deref()
+    }
+
+    pub fn scheduled_at(&self) -> Option<&str> {
+        self.scheduled_at.as_deref()
+    }
+
+    pub fn content_hash(&self) -> Option<&str> {
+        self.content_hash.as_deref()
+    }
+
+    pub fn font_path(&self) -> Option<&str> {
+        self.font_path.as_deref()
+    }
+
+    pub fn css_url(&self) -> Option<&str> {
+        self.css_url.as_deref()
+    }
+
+    fn set_title(&mut self, title: &str) {
+        self.title = title.to_string();
+    }
+
+    pub fn set_volume(&mut self, volume: Option<&str>) {
+        self.volume = volume.map(str::to_string);
+    }
+
+    pub fn set_remote_id(&mut self, remote_id: &str) {
+        self.remote_id = Some(remote_id.to_string());
+    }
+
+    pub fn set_published_at(&mut self, published_at: &str) {
+        self.published_at = Some(published_at.to_string());
+    }
+
+    pub fn set_updated_at(&mut self, updated_at: &str) {
+        self.updated_at = Some(updated_at.to_string());
+    }
+
+    pub fn set_scheduled_at(&mut self, scheduled_at: Option<String>) {
+        self.scheduled_at = scheduled_at;
+    }
+
+    pub fn set_content_hash(&mut self, content_hash: &str) {
+        self.content_hash = Some(content_hash.to_string());
+    }
+
+    pub fn set_assets(&mut self, font_path: Option<&str>, css_url: Option<&str>) {
+        self.font_path = font_path.map(str::to_string);
+        self.css_url = css_url.map(str::to_string);
+    }
+}
+
+impl Ledger {
+    // 讀取章節記錄，舊版 index,title 格式會自動遷移並保留備份
+    pub fn load(path: &Path) -> Result<Self> {
+        let (ledger, legacy) = Self::parse(path)?;
+        if legacy {
+            let backup = path.with_extension("csv.bak");
+            fs::copy(path, &backup).context("備份舊章節列表失敗")?;
+            ledger.save(path)?;
+            println!("已將章節列表遷移至新格式，舊文件備份於 {}", backup.display());
+        }
+        Ok(ledger)
+    }
+
+    // 唯讀讀取章節記錄，舊版格式只在記憶體中轉換，不修改檔案
+    pub fn read(path: &Path) -> Result<Self> {
+        Self::parse(path).map(|(ledger, _)| ledger)
+    }
+
+    // (章節記錄, 是否為舊版 index,title 格式)
+    fn parse(path: &Path) -> Result<(Self, bool)> {
+        if !path.exists() {
+            return Ok((Self::default(), false));
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("讀取章節記錄 {} 失敗", path.display()))?;
+        let trimmed = content.trim_start_matches('\u{feff}').trim();
+        if trimmed.is_empty() {
+            return Ok((Self::default(), false));
+        }
+        if trimmed.starts_with('{') {
+            let mut ledger: Self = serde_json::from_str(trimmed)
+                .with_context(|| format!("章節記錄 {} 格式錯誤", path.display()))?;
+            ledger.chapters.sort_by_key(|entry| entry.index);
+            return Ok((ledger, false));
+        }
+        Ok((Self::from_csv(trimmed), true))
+    }
+
+    fn from_csv(content: &str) -> Self {
+        let mut ledger = Self::default();
+        for line in content.lines() {
+            if let Some((index_str, title)) = line.split_once(',') {
+                if let Ok(index) = index_str.trim().parse::<usize>() {
+                    ledger.upsert(index, title.trim());
+                } else {
+                    eprintln!("無法解析：{}", index_str);
+                }
+            }
+        }
+        ledger
+    }
+
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("序列化失敗")?;
+        fs::write(path, content)
+            .with_context(|| format!("寫入章節記錄 {} 失敗", path.display()))
+    }
+
+    pub fn entries(&self) -> &[LedgerEntry] {
+        &self.chapters
+    }
+
+    pub fn get(&self, index: usize) -> Option<&LedgerEntry> {
+        self.chapters.iter().find(|entry| entry.index == index)
+    }
+
+    // 依章節編號新增或更新標題，回傳該記錄以便補充其他欄位
+    pub fn upsert(&mut self, index: usize, title: &str) -> &mut LedgerEntry {
+        let position = match self.chapters.binary_search_by_key(&index, |entry| entry.index) {
+            Ok(position) => position,
+            Err(position) => {
+                self.chapters.insert(position, LedgerEntry { index, ..LedgerEntry::default() });
+                position
+            }
+        };
+        let entry = &mut self.chapters[position];
+        entry.set_title(title);
+        entry
+    }
+
+    pub fn retain(&mut self, f: impl FnMut(&LedgerEntry) -> bool) {
+        self.chapters.retain(f);
+    }
+
+    pub fn schedule(&self) -> Option<&ScheduleState> {
+        self.schedule.as_ref()
+    }
+
+    pub fn schedule_mut(&mut self) -> &mut ScheduleState {
+        self.schedule.get_or_insert_with(ScheduleState::default)
+    }
+}
+
+// 忽略行首尾空白 (含全形空格)、空行與換行符差異，只有正文實際改動才會改變雜湊
+fn normalize(content: &str) -> String {
+    content.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(normalize(content).as_bytes()))
+}
@@ -0,0 +1,176 @@
+use crate::book::{encoding, epub, markdown, read_chapters, ChapterRule, Config, ChapterMarker, RawChapter};
+use anyhow::Result;
+use regex::Regex;
+use std::collections::HashMap;
+
+// 字數低於中位數的此比例或高於此倍數時視為異常
+const SHORT_RATIO: f64 = 0.3;
+const LONG_RATIO: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+pub struct Issue {
+    severity: Severity,
+    line: Option<usize>,
+    chapter: Option<usize>,
+    message: String,
+}
+
+pub struct LintReport {
+    issues: Vec<Issue>,
+    // (章節編號, 標題, 字數)
+    counts: Vec<(usize, String, usize)>,
+}
+
+impl LintReport {
+    pub fn has_errors(&self) -> bool {
+        self.issues.iter().any(|issue| issue.severity == Severity::Error)
+    }
+
+    pub fn print(&self) {
+        println!("{:>6}  {:>8}  標題", "章節", "字數");
+        for (index, title, count) in &self.counts {
+            println!("{:>6}  {:>8}  {}", index, count, title);
+        }
+        println!();
+
+        for issue in &self.issues {
+            let level = match issue.severity {
+                Severity::Error => "錯誤",
+                Severity::Warning => "警告",
+            };
+            let location = match (issue.line, issue.chapter) {
+                (Some(line), Some(chapter)) => format!("第 {} 行 (章節 {})", line, chapter),
+                (Some(line), None) => format!("第 {} 行", line),
+                (None, Some(chapter)) => format!("章節 {}", chapter),
+                (None, None) => String::new(),
+            };
+            println!("[{}] {}: {}", level, location, issue.message);
+        }
+
+        let errors = self.issues.iter().filter(|i| i.severity == Severity::Error).count();
+        let warnings = self.issues.len() - errors;
+        println!("共 {} 章，{} 個錯誤，{} 個警告", self.counts.len(), errors, warnings);
+    }
+
+    fn push(&mut self, severity: Severity, line: Option<usize>, chapter: Option<usize>, message: String) {
+        self.issues.push(Issue { severity, line, chapter, message });
+    }
+}
+
+fn char_count(content: &str) -> usize {
+    content.chars().filter(|c| !c.is_whitespace()).count()
+}
+
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != *cb);
+            current[j + 1] = (previous[j] + cost).min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+fn check_chapters(report: &mut LintReport, chapters: &[RawChapter]) {
+    let mut seen: HashMap<&str, (usize, Option<usize>)> = HashMap::new();
+    for (idx, chapter) in chapters.iter().enumerate() {
+        let index = idx + 1;
+        let title = chapter.title.trim();
+        if title.is_empty() {
+            report.push(Severity::Error, chapter.line, Some(index), "章節沒有標題 (可能是連續的分隔符)".to_string());
+            continue;
+        }
+        if chapter.content.trim().is_empty() {
+            report.push(Severity::Error, chapter.line, Some(index), format!("「{}」內容為空", title));
+        }
+        match seen.get(title) {
+            Some(&(first_index, first_line)) => {
+                let first = first_line.map_or(format!("章節 {}", first_index), |line| format!("第 {} 行", line));
+                report.push(Severity::Error, chapter.line, Some(index), format!("標題「{}」與 {} 重複", title, first));
+            }
+            None => {
+                seen.insert(title, (index, chapter.line));
+            }
+        }
+    }
+}
+
+// 分隔符打錯時兩章會被合併，找出與分隔符極為相似但不相同的行；
+// 只是推測 (也可能是裝飾用的分隔線)，因此只提示警告，不阻擋發布
+fn check_separators(report: &mut LintReport, text: &str, marker: &ChapterMarker) {
+    let loose_heading = Regex::new(r"^第[0-9０-９零〇一二三四五六七八九十百千萬两兩]+[章回節]").unwrap();
+    for (line_no, line) in text.lines().enumerate() {
+        let trimmed = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        match &marker.rule {
+            ChapterRule::Separator(separator) if trimmed != separator => {
+                let limit = (separator.chars().count() / 3).max(1);
+                let similar = trimmed.chars().any(|c| separator.contains(c));
+                if similar && levenshtein(trimmed, separator) <= limit {
+                    report.push(Severity::Warning, Some(line_no + 1), None,
+                                format!("「{}」與分隔符「{}」相似，可能是打錯的分隔符", trimmed, separator));
+                }
+            }
+            ChapterRule::Heading(re) if !re.is_match(trimmed) && loose_heading.is_match(trimmed) => {
+                report.push(Severity::Warning, Some(line_no + 1), None,
+                            format!("「{}」看起來像章節標題，但不符合標題格式", trimmed));
+            }
+            _ => {}
+        }
+    }
+}
+
+fn check_lengths(report: &mut LintReport) {
+    let mut sorted: Vec<usize> = report.counts.iter()
+        .map(|&(_, _, count)| count)
+        .filter(|&count| count > 0)
+        .collect();
+    if sorted.len() < 3 {
+        return;
+    }
+    sorted.sort_unstable();
+    let median = sorted[sorted.len() / 2] as f64;
+
+    let outliers: Vec<(usize, usize)> = report.counts.iter()
+        .filter(|&&(_, _, count)| {
+            count > 0 && ((count as f64) < median * SHORT_RATIO || (count as f64) > median * LONG_RATIO)
+        })
+        .map(|&(index, _, count)| (index, count))
+        .collect();
+    for (index, count) in outliers {
+        report.push(Severity::Warning, None, Some(index),
+                    format!("字數 {} 與中位數 {} 相差過大", count, median as usize));
+    }
+}
+
+pub fn lint(book_path: &str, config: &Config) -> Result<LintReport> {
+    let chapters = read_chapters(book_path, config)?;
+    let mut report = LintReport {
+        issues: Vec::new(),
+        counts: chapters.iter()
+            .enumerate()
+            .map(|(idx, chapter)| (idx + 1, chapter.title.clone(), char_count(&chapter.content)))
+            .collect(),
+    };
+
+    // 僅文字稿需要檢查分隔符
+    if !epub::is_epub(book_path) && !markdown::is_markdown_dir(book_path) {
+        let text = encoding::read_text(book_path, config.encoding.as_deref())?;
+        check_separators(&mut report, &text, &ChapterMarker::build(config)?);
+    }
+    check_chapters(&mut report, &chapters);
+    check_lengths(&mut report);
+    report.issues.sort_by_key(|issue| (issue.line.unwrap_or(usize::MAX), issue.chapter));
+    Ok(report)
+}
@@ -0,0 +1,313 @@
+use crate::book::store::BookStore;
+use crate::book::{convert, epub, markdown, prompt_id, ChapterMarker, Config, Ledger, CHAPTER_DIR};
+use crate::utils::get_input;
+use anyhow::{anyhow, Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const MANIFEST_FILE: &str = "book.toml";
+
+const USAGE: &str = "用法: book add <書籍路徑> [選項] | book list | book show <書籍> | book edit <書籍> [選項] | book remove <書籍>
+      book relink <書籍編號或舊路徑> <新路徑>
+選項: --book-id <編號> --forum-id <編號> --encrypt | --no-encrypt --separator <分隔符>
+      --pattern <標題格式> --volume-pattern <分卷格式> --encoding <編碼> --s2t | --no-s2t --phrase-dict <路徑>
+      --timezone <輸入時區>";
+
+// 書籍設定檔 (與稿件放在一起) 或命令列參數，未提供的欄位為 None
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct BookManifest {
+    book_id: Option<String>,
+    forum_id: Option<String>,
+    encrypt: Option<bool>,
+    chapter_separator: Option<String>,
+    chapter_pattern: Option<String>,
+    volume_pattern: Option<String>,
+    encoding: Option<String>,
+    s2t: Option<bool>,
+    phrase_dict: Option<PathBuf>,
+    timezone: Option<String>,
+}
+
+impl BookManifest {
+    // novel.txt -> novel.book.toml；Markdown 目錄則為目錄內的 book.toml
+    pub fn path(book_path: &str) -> PathBuf {
+        let path = Path::new(book_path);
+        if path.is_dir() {
+            return path.join(MANIFEST_FILE);
+        }
+        let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+        path.with_file_name(format!("{}.{}", stem, MANIFEST_FILE))
+    }
+
+    pub fn load(book_path: &str) -> Result<Option<Self>> {
+        let path = Self::path(book_path);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("讀取書籍設定檔 {} 失敗", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("書籍設定檔 {} 格式錯誤", path.display()))
+            .map(Some)
+    }
+
+    pub fn parse_args(args: &[String]) -> Result<Self> {
+        let mut manifest = Self::default();
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            let mut value = || iter.next().cloned().ok_or_else(|| anyhow!("{} 缺少參數值", arg));
+            match arg.as_str() {
+                "--book-id" => manifest.book_id = Some(value()?),
+                "--forum-id" => manifest.forum_id = Some(value()?),
+                "--encrypt" => manifest.encrypt = Some(true),
+                "--no-encrypt" => manifest.encrypt = Some(false),
+                "--separator" => manifest.chapter_separator = Some(value()?),
+                "--pattern" => manifest.chapter_pattern = Some(value()?),
+                "--volume-pattern" => manifest.volume_pattern = Some(value()?),
+                "--encoding" => manifest.encoding = Some(value()?),
+                "--s2t" => manifest.s2t = Some(true),
+                "--no-s2t" => manifest.s2t = Some(false),
+                "--phrase-dict" => manifest.phrase_dict = Some(PathBuf::from(value()?)),
+                "--timezone" => manifest.timezone = Some(value()?),
+                _ => return Err(anyhow!("未知的選項: {}\n{}", arg, USAGE)),
+            }
+        }
+        Ok(manifest)
+    }
+
+    // 互動登記時的回答直接填入欄位，分隔符等以 -- 開頭的回答不會被當成選項
+    pub fn prompt(book_path: &str, book_id: String) -> Self {
+        let forum_id = prompt_id("請輸入論壇編號: ", "論壇編號");
+        let encrypt = get_input("是否加密書籍(y/n): ").to_lowercase() == "y";
+        let mut manifest = Self {
+            book_id: Some(book_id),
+            forum_id: Some(forum_id),
+            encrypt: Some(encrypt),
+            ..Self::default()
+        };
+        // EPUB 與 Markdown 目錄本身已分章，不需要分隔符或標題格式
+        if !is_pre_split(book_path) {
+            let chapter_pattern = get_input("請輸入章節標題格式 (chinese / english / 正則表達式，留空則使用分隔符): ");
+            if chapter_pattern.is_empty() {
+                manifest.chapter_separator = Some(get_input("請輸入章節分隔符: "));
+            } else {
+                manifest.chapter_pattern = Some(chapter_pattern);
+            }
+            manifest.volume_pattern = Some(get_input("請輸入分卷標題格式 (chinese / english / 正則表達式，留空則不分卷): "));
+        }
+        if get_input("是否將簡體轉換為繁體 (y/n，默認 n): ").to_lowercase() == "y" {
+            manifest.s2t = Some(true);
+            manifest.phrase_dict = Some(PathBuf::from(get_input("請輸入自訂詞典路徑 (留空則不使用): ")));
+        }
+        manifest
+    }
+
+    // other 中有值的欄位覆蓋 self
+    pub fn merge(self, other: Self) -> Self {
+        Self {
+            book_id: other.book_id.or(self.book_id),
+            forum_id: other.forum_id.or(self.forum_id),
+            encrypt: other.encrypt.or(self.encrypt),
+            chapter_separator: other.chapter_separator.or(self.chapter_separator),
+            chapter_pattern: other.chapter_pattern.or(self.chapter_pattern),
+            volume_pattern: other.volume_pattern.or(self.volume_pattern),
+            encoding: other.encoding.or(self.encoding),
+            s2t: other.s2t.or(self.s2t),
+            phrase_dict: other.phrase_dict.or(self.phrase_dict),
+            timezone: other.timezone.or(self.timezone),
+        }
+    }
+
+    // 套用到現有設定，空字串表示清除該欄位
+    fn apply(self, config: &mut Config) {
+        let optional = |value: String| (!value.is_empty()).then_some(value);
+        if let Some(book_id) = self.book_id {
+            config.book_id = book_id;
+        }
+        if let Some(forum_id) = self.forum_id {
+            config.forum_id = forum_id;
+        }
+        if let Some(encrypt) = self.encrypt {
+            config.encrypt = encrypt;
+        }
+        if let Some(separator) = self.chapter_separator {
+            config.chapter_separator = separator;
+        }
+        if let Some(pattern) = self.chapter_pattern {
+            config.chapter_pattern = optional(pattern);
+        }
+        if let Some(pattern) = self.volume_pattern {
+            config.volume_pattern = optional(pattern);
+        }
+        if let Some(encoding) = self.encoding {
+            config.encoding = optional(encoding);
+        }
+        if let Some(s2t) = self.s2t {
+            config.s2t = s2t;
+        }
+        if let Some(path) = self.phrase_dict {
+            config.phrase_dict = (!path.as_os_str().is_empty()).then_some(path);
+        }
+        if let Some(timezone) = self.timezone {
+            config.timezone = optional(timezone);
+        }
+    }
+
+    fn into_config(self) -> Result<Config> {
+        let book_id = self.book_id.clone().ok_or(anyhow!("缺少書籍編號 (book_id)"))?;
+        let forum_id = self.forum_id.clone().ok_or(anyhow!("缺少論壇編號 (forum_id)"))?;
+        let mut config = Config {
+            chapter_list: Path::new(CHAPTER_DIR).join(&book_id),
+            book_id,
+            forum_id,
+            encrypt: false,
+            chapter_separator: String::new(),
+            chapter_pattern: None,
+            volume_pattern: None,
+            encoding: None,
+            s2t: false,
+            phrase_dict: None,
+            timezone: None,
+            book_path: String::new(),
+        };
+        self.apply(&mut config);
+        Ok(config)
+    }
+}
+
+pub fn validate_id(label: &str, id: &str) -> Result<()> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_digit()) {
+        return Err(anyhow!("{}必須為數字: {:?}", label, id));
+    }
+    Ok(())
+}
+
+fn is_pre_split(book_path: &str) -> bool {
+    epub::is_epub(book_path) || markdown::is_markdown_dir(book_path)
+}
+
+// 文字稿 (require_rule) 需要章節分隔符或標題格式；EPUB 與 Markdown 目錄本身已分章
+fn validate(config: &Config, require_rule: bool) -> Result<()> {
+    validate_id("書籍編號", &config.book_id)?;
+    validate_id("論壇編號", &config.forum_id)?;
+    if require_rule && !has_rule(config) {
+        return Err(anyhow!("需要章節分隔符或章節標題格式"));
+    }
+    ChapterMarker::build(config)?;
+    config.timezone()?;
+    if config.s2t {
+        convert::check_dictionaries()?;
+    }
+    Ok(())
+}
+
+fn has_rule(config: &Config) -> bool {
+    config.chapter_pattern.is_some() || !config.chapter_separator.is_empty()
+}
+
+// 只讀取設定檔建立設定並檢查，不登記書籍 (唯讀命令使用)
+pub fn preview(book_path: &str, manifest: BookManifest) -> Result<Config> {
+    let config = manifest.into_config()?;
+    validate(&config, !is_pre_split(book_path))?;
+    Ok(config)
+}
+
+// 登記新書籍並建立章節列表，已存在的章節列表不會被覆蓋
+pub fn register(book_path: &str, manifest: BookManifest) -> Result<Config> {
+    let mut store = BookStore::load()?;
+    if let Some(book_id) = store.lookup(book_path)? {
+        return Err(anyhow!("{} 已登記為書籍 {}", book_path, book_id));
+    }
+    let config = manifest.into_config()?;
+    if let Some(paths) = store.get(&config.book_id).map(|_| store.paths(&config.book_id)) {
+        return Err(anyhow!("書籍編號 {} 已登記 (路徑: {})，若稿件已移動請使用 book relink {} {}",
+                           config.book_id, paths.join(", "), config.book_id, book_path));
+    }
+    validate(&config, !is_pre_split(book_path))?;
+
+    if !config.chapter_list.exists() {
+        fs::create_dir_all(CHAPTER_DIR)
+            .context("無法創建目錄")?;
+        Ledger::default().save(&config.chapter_list)
+            .context("無法創建文件")?;
+    }
+    store.link(book_path, &config.book_id)?;
+    store.insert(config.clone());
+    store.save()?;
+    Ok(config)
+}
+
+fn print_book(store: &BookStore, book_id: &str) -> Result<()> {
+    let config = store.get(book_id).ok_or_else(|| anyhow!("找不到書籍 {}", book_id))?;
+    println!("[{}]", book_id);
+    let table = toml::to_string_pretty(config).context("序列化失敗")?;
+    println!("{}", table.trim_end());
+    for path in store.paths(book_id) {
+        println!("路徑: {}", path);
+    }
+    Ok(())
+}
+
+// 命令列書籍管理：add / list / show / edit / remove / relink，書籍可用書籍編號或稿件路徑指定
+pub fn manage(args: &[String]) -> Result<()> {
+    let target = || args.get(1).ok_or_else(|| anyhow!("缺少書籍\n{}", USAGE));
+    let mut store = BookStore::load()?;
+    match args.first().map(String::as_str) {
+        Some("add") => {
+            let book_path = target()?;
+            let manifest = BookManifest::load(book_path)?
+                .unwrap_or_default()
+                .merge(BookManifest::parse_args(&args[2..])?);
+            let config = register(book_path, manifest)?;
+            println!("已新增書籍");
+            print_book(&BookStore::load()?, &config.book_id)?;
+        }
+        Some("list") => {
+            println!("{:>12}  {:>8}  {:<4}  路徑", "書籍編號", "論壇編號", "加密");
+            for (book_id, config) in store.books() {
+                println!("{:>12}  {:>8}  {:<4}  {}",
+                         book_id, config.forum_id, if config.encrypt { "是" } else { "否" },
+                         store.paths(book_id).join(", "));
+            }
+        }
+        Some("show") => {
+            let book_id = store.resolve(target()?)?;
+            print_book(&store, &book_id)?;
+        }
+        Some("edit") => {
+            let book_id = store.resolve(target()?)?;
+            let mut config = store.get(&book_id).cloned().ok_or_else(|| anyhow!("找不到書籍 {}", book_id))?;
+            // 以書籍編號指定時無法判斷稿件類型，沿用原設定是否有分章規則
+            let require_rule = !is_pre_split(target()?) && has_rule(&config);
+            BookManifest::parse_args(&args[2..])?.apply(&mut config);
+            validate(&config, require_rule)?;
+            if config.book_id != book_id && store.get(&config.book_id).is_some() {
+                return Err(anyhow!("書籍編號 {} 已被使用", config.book_id));
+            }
+            let new_id = config.book_id.clone();
+            store.rekey(&book_id, config);
+            store.save()?;
+            println!("已更新書籍");
+            print_book(&store, &new_id)?;
+        }
+        Some("remove") => {
+            let book_id = store.resolve(target()?)?;
+            let config = store.remove(&book_id).ok_or_else(|| anyhow!("找不到書籍 {}", book_id))?;
+            store.save()?;
+            println!("已移除書籍 {}，章節列表保留於 {}", book_id, config.chapter_list.display());
+        }
+        Some("relink") => {
+            let old = target()?;
+            let new_path = args.get(2).ok_or_else(|| anyhow!("缺少新的稿件路徑\n{}", USAGE))?;
+            let book_id = store.resolve(old)?;
+            let old_path = (book_id != *old).then_some(old.as_str());
+            store.relink(&book_id, old_path, new_path)?;
+            store.save()?;
+            println!("已將 {} 連結至書籍 {}", new_path, book_id);
+        }
+        _ => return Err(anyhow!("{}", USAGE)),
+    }
+    Ok(())
+}
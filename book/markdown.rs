@@ -0,0 +1,182 @@
+use crate::book::{encoding, RawChapter, Volume};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDateTime, TimeZone};
+use chrono_tz::Tz;
+use regex::Regex;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const DATETIME_FORMATS: [&str; 4] = ["%Y-%m-%d %H:%M", "%Y-%m-%d %H:%M:%S", "%Y-%m-%dT%H:%M", "%Y-%m-%dT%H:%M:%S"];
+
+// notes 等其他欄位僅供作者參考，不會發布
+#[derive(Deserialize, Default)]
+struct FrontMatter {
+    title: Option<String>,
+    index: Option<usize>,
+    volume: Option<serde_yaml::Value>,
+    schedule: Option<String>,
+}
+
+struct Manuscript {
+    file_name: String,
+    front: FrontMatter,
+    body: String,
+}
+
+pub fn is_markdown_dir(book_path: &str) -> bool {
+    Path::new(book_path).is_dir()
+}
+
+fn is_markdown_file(path: &Path) -> bool {
+    path.is_file() && path.extension().is_some_and(|ext| {
+        ext.eq_ignore_ascii_case("md") || ext.eq_ignore_ascii_case("markdown")
+    })
+}
+
+fn split_front_matter(text: &str) -> (Option<&str>, &str) {
+    let text = text.trim_start_matches('\u{feff}');
+    let Some(rest) = text.strip_prefix("---") else {
+        return (None, text);
+    };
+    let Some(rest) = rest.strip_prefix('\n').or_else(|| rest.strip_prefix("\r\n")) else {
+        return (None, text);
+    };
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+fn read_manuscript(path: &Path, encoding_label: Option<&str>) -> Result<Manuscript> {
+    let path_str = path.to_str().ok_or(anyhow!("路徑轉換失敗"))?;
+    let text = encoding::read_text(path_str, encoding_label)?;
+    let (front, body) = split_front_matter(&text);
+    let front = match front {
+        Some(yaml) if !yaml.trim().is_empty() => serde_yaml::from_str(yaml)
+            .with_context(|| format!("{} 的 front matter 格式錯誤", path.display()))?,
+        _ => FrontMatter::default(),
+    };
+    let file_name = path.file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    Ok(Manuscript { file_name, front, body: body.to_string() })
+}
+
+// 預約時間以使用者的輸入時區解讀
+fn parse_schedule(value: &str, tz: Tz) -> Result<DateTime<Tz>> {
+    let value = value.trim();
+    let naive = DATETIME_FORMATS.iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(value, format).ok())
+        .ok_or_else(|| anyhow!("預約時間格式錯誤 (應為 YYYY-MM-DD HH:MM): {}", value))?;
+    tz.from_local_datetime(&naive)
+        .single()
+        .ok_or_else(|| anyhow!("無法將 {} 轉換為 {} 時區時間", value, tz.name()))
+}
+
+fn volume_title(value: &serde_yaml::Value) -> Option<String> {
+    match value {
+        serde_yaml::Value::String(title) => Some(title.trim().to_string()),
+        serde_yaml::Value::Number(number) => Some(number.to_string()),
+        _ => None,
+    }
+    .filter(|title| !title.is_empty())
+}
+
+// 去除 Markdown 標記，輸出與文字稿相同的純文字
+fn plain_text(body: &str, title: &str) -> String {
+    let comment = Regex::new(r"(?s)<!--.*?-->").unwrap();
+    let image = Regex::new(r"!\[[^\]]*\]\([^)]*\)").unwrap();
+    let link = Regex::new(r"\[([^\]]*)\]\([^)]*\)").unwrap();
+    let strong = Regex::new(r"(\*\*|__)(.+?)(\*\*|__)").unwrap();
+    let emphasis = Regex::new(r"\*([^*\s][^*]*)\*").unwrap();
+    let heading = Regex::new(r"^#{1,6}\s+").unwrap();
+    let quote = Regex::new(r"^>\s?").unwrap();
+
+    let body = comment.replace_all(body, "");
+    let mut lines = Vec::new();
+    let mut title_skipped = false;
+    for line in body.lines() {
+        let line = line.trim_end();
+        let is_heading = heading.is_match(line);
+        let line = heading.replace(line, "");
+        let line = quote.replace(&line, "");
+        let line = image.replace_all(&line, "");
+        let line = link.replace_all(&line, "$1");
+        let line = strong.replace_all(&line, "$2");
+        let line = emphasis.replace_all(&line, "$1").to_string();
+        // 與章節標題相同的第一個標題行已由標題表示
+        if !title_skipped && is_heading && line.trim() == title {
+            title_skipped = true;
+            continue;
+        }
+        if lines.is_empty() && line.trim().is_empty() {
+            continue;
+        }
+        lines.push(line);
+    }
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+fn first_heading(body: &str) -> Option<String> {
+    body.lines()
+        .find_map(|line| line.trim().strip_prefix("# "))
+        .map(|title| title.trim().to_string())
+        .filter(|title| !title.is_empty())
+}
+
+pub fn read_chapters(book_path: &str, encoding_label: Option<&str>, tz: Tz) -> Result<Vec<RawChapter>> {
+    let mut paths: Vec<PathBuf> = fs::read_dir(book_path)
+        .context("無法讀取稿件目錄")?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| is_markdown_file(path))
+        .collect();
+    paths.sort();
+
+    let mut manuscripts = paths.iter()
+        .map(|path| read_manuscript(path, encoding_label))
+        .collect::<Result<Vec<_>>>()?;
+    // 全部稿件都有 index 時按 index 排序，否則按檔名排序
+    if manuscripts.iter().all(|m| m.front.index.is_some()) {
+        manuscripts.sort_by_key(|m| m.front.index);
+    }
+
+    let mut chapters = Vec::new();
+    let mut current_volume: Option<Volume> = None;
+    for manuscript in manuscripts {
+        let title = manuscript.front.title.clone()
+            .or_else(|| first_heading(&manuscript.body))
+            .unwrap_or_else(|| {
+                Path::new(&manuscript.file_name).file_stem()
+                    .map(|stem| stem.to_string_lossy().to_string())
+                    .unwrap_or_default()
+            });
+        if let Some(volume) = manuscript.front.volume.as_ref().and_then(volume_title) {
+            if current_volume.as_ref().is_none_or(|current| current.title != volume) {
+                let index = current_volume.as_ref().map_or(1, |v| v.index + 1);
+                current_volume = Some(Volume { index, title: volume });
+            }
+        }
+        let schedule = manuscript.front.schedule.as_deref()
+            .map(|value| parse_schedule(value, tz))
+            .transpose()
+            .with_context(|| format!("稿件 {}", manuscript.file_name))?;
+        chapters.push(RawChapter {
+            content: plain_text(&manuscript.body, &title),
+            title,
+            volume: current_volume.clone(),
+            schedule,
+            line: None,
+        });
+    }
+    Ok(chapters)
+}
@@ -0,0 +1,325 @@
+use crate::book::lint::levenshtein;
+use crate::book::RawChapter;
+use crate::utils::get_input;
+use regex::Regex;
+use std::collections::HashMap;
+
+// 相似度低於此值不視為同一章節
+const SIMILARITY_THRESHOLD: f64 = 0.6;
+// 最佳與次佳候選的相似度差距小於此值時需由使用者判斷
+const AMBIGUOUS_MARGIN: f64 = 0.1;
+const MAX_CANDIDATES: usize = 3;
+// 章節序號相同時的最低相似度 (例如副標題有修改)
+const SAME_NUMBER_SCORE: f64 = 0.8;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchKind {
+    Exact,
+    Fuzzy,
+    Ambiguous,
+    Unmatched,
+}
+
+pub struct TitleMatch {
+    remote_title: String,
+    remote_id: String,
+    kind: MatchKind,
+    // (本地章節編號, 相似度)，由高至低
+    candidates: Vec<(usize, f64)>,
+    index: Option<usize>,
+}
+
+impl TitleMatch {
+    pub fn remote_title(&self) -> &str {
+        &self.remote_title
+    }
+
+    pub fn remote_id(&self) -> &str {
+        &self.remote_id
+    }
+
+    pub fn index(&self) -> Option<usize> {
+        self.index
+    }
+}
+
+fn numeral_value(c: char) -> Option<usize> {
+    match c {
+        '零' | '〇' => Some(0),
+        '一' => Some(1),
+        '二' | '兩' | '两' => Some(2),
+        '三' => Some(3),
+        '四' => Some(4),
+        '五' => Some(5),
+        '六' => Some(6),
+        '七' => Some(7),
+        '八' => Some(8),
+        '九' => Some(9),
+        _ => None,
+    }
+}
+
+fn numeral_unit(c: char) -> Option<usize> {
+    match c {
+        '十' => Some(10),
+        '百' => Some(100),
+        '千' => Some(1000),
+        '萬' | '万' => Some(10000),
+        _ => None,
+    }
+}
+
+// 十二 -> 12、一百零五 -> 105、二〇二六 -> 2026，數值溢出時回傳 None (不視為數字)
+fn chinese_number(numeral: &[char]) -> Option<usize> {
+    if !numeral.iter().any(|&c| numeral_unit(c).is_some()) {
+        return numeral.iter()
+            .filter_map(|&c| numeral_value(c))
+            .try_fold(0usize, |acc, digit| acc.checked_mul(10)?.checked_add(digit));
+    }
+    let (mut total, mut section, mut digit) = (0usize, 0usize, 0usize);
+    for &c in numeral {
+        match (numeral_value(c), numeral_unit(c)) {
+            (Some(value), _) => digit = value,
+            (_, Some(10000)) => {
+                total = section.checked_add(digit)?.checked_mul(10000)?.checked_add(total)?;
+                section = 0;
+                digit = 0;
+            }
+            (_, Some(unit)) => {
+                section = digit.max(1).checked_mul(unit)?.checked_add(section)?;
+                digit = 0;
+            }
+            _ => {}
+        }
+    }
+    total.checked_add(section)?.checked_add(digit)
+}
+
+// 全形轉半形、去除空白、統一大小寫，並將中文數字轉為阿拉伯數字
+pub fn normalize_title(title: &str) -> String {
+    let mut chars = Vec::new();
+    for c in title.chars() {
+        let c = match c {
+            '\u{3000}' => ' ',
+            '\u{FF01}'..='\u{FF5E}' => char::from_u32(c as u32 - 0xFEE0).unwrap_or(c),
+            c => c,
+        };
+        if !c.is_whitespace() {
+            chars.extend(c.to_lowercase());
+        }
+    }
+
+    let mut normalized = String::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        while i < chars.len() && (numeral_value(chars[i]).is_some() || numeral_unit(chars[i]).is_some()) {
+            i += 1;
+        }
+        if i > start {
+            match chinese_number(&chars[start..i]) {
+                Some(number) => normalized.push_str(&number.to_string()),
+                None => normalized.extend(&chars[start..i]),
+            }
+        } else {
+            normalized.push(chars[i]);
+            i += 1;
+        }
+    }
+    normalized
+}
+
+fn chapter_number(re: &Regex, normalized: &str) -> Option<usize> {
+    re.captures(normalized).and_then(|caps| caps[1].parse().ok())
+}
+
+// 章節序號不同的標題 (第1章 / 第2章) 即使字面相近也不應對應
+fn similarity(re: &Regex, a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 0.0;
+    }
+    let score = 1.0 - levenshtein(a, b) as f64 / longest as f64;
+    match (chapter_number(re, a), chapter_number(re, b)) {
+        (Some(x), Some(y)) if x == y => score.max(SAME_NUMBER_SCORE),
+        (Some(_), Some(_)) => score * 0.5,
+        _ => score,
+    }
+}
+
+fn classify(candidates: &[(usize, f64)]) -> (MatchKind, Option<usize>) {
+    match candidates {
+        [] => (MatchKind::Unmatched, None),
+        [(index, score), rest @ ..] => {
+            let runner_up = rest.first().map_or(0.0, |&(_, score)| score);
+            if *score >= 1.0 && runner_up < 1.0 {
+                (MatchKind::Exact, Some(*index))
+            } else if *score < SIMILARITY_THRESHOLD {
+                (MatchKind::Unmatched, None)
+            } else if *score - runner_up < AMBIGUOUS_MARGIN {
+                (MatchKind::Ambiguous, Some(*index))
+            } else {
+                (MatchKind::Fuzzy, Some(*index))
+            }
+        }
+    }
+}
+
+// 以正規化後的標題計算相似度，為每個遠端章節找出最可能對應的本地章節
+pub fn match_titles(remote: HashMap<String, String>, chapters: &[RawChapter]) -> Vec<TitleMatch> {
+    let number = Regex::new(r"第(\d+)[章回節话話]").unwrap();
+    let local: Vec<(usize, String)> = chapters.iter()
+        .enumerate()
+        .filter(|(_, chapter)| !chapter.title.trim().is_empty())
+        .map(|(idx, chapter)| (idx + 1, normalize_title(&chapter.title)))
+        .collect();
+
+    let mut matches: Vec<TitleMatch> = remote.into_iter()
+        .map(|(remote_title, remote_id)| {
+            let normalized = normalize_title(&remote_title);
+            let mut candidates: Vec<(usize, f64)> = local.iter()
+                .map(|(index, title)| (*index, similarity(&number, &normalized, title)))
+                .filter(|&(_, score)| score > 0.0)
+                .collect();
+            candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+            candidates.truncate(MAX_CANDIDATES);
+            let (kind, index) = classify(&candidates);
+            TitleMatch { remote_title, remote_id, kind, candidates, index }
+        })
+        .collect();
+
+    // 多個遠端章節對應到同一本地章節時，非完全相符者改為待確認
+    let mut claims: HashMap<usize, usize> = HashMap::new();
+    for index in matches.iter().filter_map(|m| m.index) {
+        *claims.entry(index).or_insert(0) += 1;
+    }
+    for m in matches.iter_mut() {
+        if m.kind == MatchKind::Fuzzy && m.index.is_some_and(|index| claims[&index] > 1) {
+            m.kind = MatchKind::Ambiguous;
+        }
+    }
+
+    matches.sort_by(|a, b| {
+        a.index.unwrap_or(usize::MAX).cmp(&b.index.unwrap_or(usize::MAX))
+            .then_with(|| a.remote_title.cmp(&b.remote_title))
+    });
+    matches
+}
+
+fn kind_label(kind: MatchKind) -> &'static str {
+    match kind {
+        MatchKind::Exact => "相符",
+        MatchKind::Fuzzy => "近似",
+        MatchKind::Ambiguous => "待確認",
+        MatchKind::Unmatched => "無對應",
+    }
+}
+
+fn local_title(chapters: &[RawChapter], index: usize) -> &str {
+    chapters.get(index - 1).map_or("", |chapter| chapter.title.as_str())
+}
+
+pub fn print_report(matches: &[TitleMatch], chapters: &[RawChapter]) {
+    println!("{:<6}  {:>6}  {:>6}  遠端標題 -> 本地標題", "狀態", "章節", "相似度");
+    for m in matches.iter().filter(|m| m.kind != MatchKind::Exact) {
+        match m.index {
+            Some(index) => {
+                let score = m.candidates.first().map_or(0.0, |&(_, score)| score);
+                println!("{:<6}  {:>6}  {:>6.2}  {} -> {}",
+                         kind_label(m.kind), index, score, m.remote_title, local_title(chapters, index));
+            }
+            None => println!("{:<6}  {:>6}  {:>6}  {}", kind_label(m.kind), "-", "-", m.remote_title),
+        }
+    }
+
+    let count = |kind| matches.iter().filter(|m| m.kind == kind).count();
+    println!("相符 {}，近似 {}，待確認 {}，無對應 {}",
+             count(MatchKind::Exact), count(MatchKind::Fuzzy),
+             count(MatchKind::Ambiguous), count(MatchKind::Unmatched));
+
+    let unclaimed: Vec<usize> = (1..=chapters.len())
+        .filter(|index| !matches.iter().any(|m| m.index == Some(*index)))
+        .collect();
+    if !unclaimed.is_empty() {
+        println!("以下本地章節沒有對應的遠端章節: {:?}", unclaimed);
+    }
+}
+
+fn review_one(m: &mut TitleMatch, chapters: &[RawChapter]) {
+    println!("[{}] 遠端標題: {}", kind_label(m.kind), m.remote_title);
+    for (index, score) in &m.candidates {
+        println!("    {:>4}  {:.2}  {}", index, score, local_title(chapters, *index));
+    }
+    let default = m.index.map_or("s".to_string(), |index| index.to_string());
+    loop {
+        let input = get_input(&format!("對應的本地章節編號 (s 略過，默認 {}): ", default));
+        let input = if input.is_empty() { default.as_str() } else { input.as_str() };
+        if input.eq_ignore_ascii_case("s") {
+            m.index = None;
+            return;
+        }
+        match input.parse::<usize>() {
+            Ok(index) if (1..=chapters.len()).contains(&index) => {
+                m.index = Some(index);
+                return;
+            }
+            _ => println!("請輸入 1 到 {} 之間的章節編號或 s", chapters.len()),
+        }
+    }
+}
+
+// 回傳 false 表示使用者取消同步
+pub fn review(matches: &mut [TitleMatch], chapters: &[RawChapter]) -> bool {
+    let needs_review = matches.iter().any(|m| m.kind != MatchKind::Exact);
+    let prompt = if needs_review {
+        "y 接受建議對應，r 逐項確認非完全相符的章節，a 逐項確認全部章節，n 取消 (默認 r): "
+    } else {
+        "y 接受對應，a 逐項確認全部章節，n 取消 (默認 y): "
+    };
+    let choice = get_input(prompt).to_lowercase();
+    let choice = match choice.as_str() {
+        "" if needs_review => "r",
+        "" => "y",
+        choice => choice,
+    };
+    match choice {
+        "n" => return false,
+        "r" | "a" => {
+            for m in matches.iter_mut().filter(|m| choice == "a" || m.kind != MatchKind::Exact) {
+                review_one(m, chapters);
+            }
+        }
+        // 未確認的待確認項目不自動套用
+        _ => {
+            for m in matches.iter_mut().filter(|m| m.kind == MatchKind::Ambiguous) {
+                m.index = None;
+            }
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(text: &str) -> Option<usize> {
+        chinese_number(&text.chars().collect::<Vec<_>>())
+    }
+
+    #[test]
+    fn chinese_numbers() {
+        assert_eq!(number("十二"), Some(12));
+        assert_eq!(number("一百零五"), Some(105));
+        assert_eq!(number("二〇二六"), Some(2026));
+        assert_eq!(number("三萬五千"), Some(35000));
+    }
+
+    #[test]
+    fn overflowing_numerals_are_not_numbers() {
+        assert_eq!(number(&"九".repeat(40)), None);
+        let title = format!("第{}章", "九".repeat(40));
+        assert_eq!(normalize_title(&title), title);
+        assert_eq!(normalize_title("第十二章 標題"), "第12章標題");
+    }
+}
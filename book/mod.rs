@@ -1,39 +1,97 @@
+mod calendar;
 mod commit;
+mod convert;
+mod diff;
+mod encoding;
+mod encrypt;
+mod epub;
+mod font;
+mod ledger;
+mod lint;
+mod manifest;
+mod markdown;
+mod matching;
+mod progress;
+mod queue;
+mod registry;
+mod review;
+mod schedule;
+mod select;
+mod store;
+mod timespec;
 
 use crate::esj::ScheduleInfo;
-use crate::utils::{get_executable_name, get_input};
+use crate::utils::{get_input, get_raw_input};
 use anyhow::{anyhow, Context, Result};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::{BTreeMap, HashMap};
 use std::fs;
-use std::fs::{File, OpenOptions};
-use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::fs::File;
+use std::io::Write;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use tokio::sync::Semaphore;
 use crate::book::commit::commit;
-use chrono::{Datelike, Duration, NaiveDate, NaiveTime, Timelike, TimeZone, Utc};
-use chrono_tz::Asia::Taipei;
+use crate::book::encrypt::{EncryptSettings, Encryptor};
+use crate::book::progress::Progress;
+use crate::book::schedule::{format_slot, ScheduleRule, WeeklyPattern, SITE_TIMEZONE};
+pub use crate::book::calendar::export_calendar;
+pub use crate::book::diff::DiffReport;
+pub use crate::book::ledger::{Ledger, LedgerEntry};
+pub use crate::book::lint::LintReport;
+pub use crate::book::manifest::{manage, BookManifest};
+pub use crate::book::queue::{manage_queue, Queue, QueueBatch, QueueSettings};
+pub use crate::book::registry::{reservations, Registry};
+pub use crate::book::review::review_schedules;
+pub use crate::book::store::BookStore;
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
 
 pub const EXECUTABLE_FILE: &str = "encryptor/encryptor";
 pub const CONFIG_FILE: &str = "book.json";
 
 pub const CHAPTER_DIR: &str = "chapters";
 pub const WORK_DIR: &str = "temp";
-type ConfigMap = HashMap<String, Config>;
+pub type VolumeGroup = (Option<Volume>, Vec<(usize, String)>);
 #[derive(Serialize, Deserialize, Clone)]
 pub struct Config {
     book_id: String,
     forum_id: String,
     encrypt: bool,
     chapter_separator: String,
+    #[serde(default)]
+    chapter_pattern: Option<String>,
+    #[serde(default)]
+    volume_pattern: Option<String>,
+    #[serde(default)]
+    encoding: Option<String>,
+    #[serde(default)]
+    s2t: bool,
+    #[serde(default)]
+    phrase_dict: Option<PathBuf>,
+    // 輸入預約時間所用的時區，未設定時使用全域設定
+    #[serde(default)]
+    timezone: Option<String>,
     chapter_list: PathBuf,
+    // 本次使用的稿件路徑，不寫入 book.json
+    #[serde(skip)]
+    book_path: String,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Volume {
+    index: usize,
+    title: String,
 }
 
 #[derive(Clone)]
 pub struct Chapter {
     index: usize,
     title: String,
+    volume: Option<Volume>,
+    schedule: Option<DateTime<Tz>>,
+    content_hash: String,
     target_path: String,
     font_path: Option<String>,
     css_url: Option<String>,
@@ -41,13 +99,15 @@ pub struct Chapter {
 
 // --- 新增：時間間隔結構體 ---
 #[derive(Debug, Clone, Copy)] // 讓它可以被複製
-struct ScheduleInterval {
+pub struct ScheduleInterval {
     days: i64,
     hours: i64,
     minutes: i64,
 }
 
 impl ScheduleInterval {
+    const ZERO: Self = Self { days: 0, hours: 0, minutes: 0 };
+
     // 將間隔轉換為 chrono::Duration
     fn to_duration(self) -> Result<Duration> {
         // 基本驗證確保值在合理範圍內，雖然 chrono::Duration 內部會處理溢出
@@ -65,6 +125,181 @@ impl ScheduleInterval {
 }
 // --- 結束新增 ---
 
+// --- 新增：章節標記 (分隔行或標題格式) ---
+const CHINESE_HEADING: &str = r"^第[0-9０-９零〇一二三四五六七八九十百千萬两兩]+[章回節](?:[\s:：、.．].*)?$";
+const ENGLISH_HEADING: &str = r"(?i)^chapter\s*\d+\b.*$";
+const CHINESE_VOLUME: &str = r"^第[0-9０-９零〇一二三四五六七八九十百千萬两兩]+[卷部](?:[\s:：、.．].*)?$";
+const ENGLISH_VOLUME: &str = r"(?i)^(volume|vol\.|part)\s*\d+\b.*$";
+
+enum ChapterRule {
+    Separator(String),
+    Heading(Regex),
+}
+
+struct ChapterMarker {
+    rule: ChapterRule,
+    volume: Option<Regex>,
+}
+
+enum LineKind {
+    Content,
+    Separator,
+    Heading(String),
+    Volume(String),
+}
+
+fn build_pattern(pattern: &str, chinese: &'static str, english: &'static str) -> Result<Regex> {
+    let pattern = match pattern {
+        "chinese" => chinese,
+        "english" => english,
+        custom => custom,
+    };
+    Regex::new(pattern).with_context(|| format!("標題格式錯誤: {}", pattern))
+}
+
+// 有名為 title 的捕獲組時以其作為標題，否則使用整行
+fn capture_title(re: &Regex, trimmed: &str) -> Option<String> {
+    re.captures(trimmed).map(|caps| {
+        caps.name("title").map_or(trimmed, |m| m.as_str().trim()).to_string()
+    })
+}
+
+impl ChapterMarker {
+    fn build(config: &Config) -> Result<Self> {
+        let rule = match config.chapter_pattern.as_deref().map(str::trim) {
+            Some(pattern) if !pattern.is_empty() => {
+                ChapterRule::Heading(build_pattern(pattern, CHINESE_HEADING, ENGLISH_HEADING)?)
+            }
+            _ => ChapterRule::Separator(config.chapter_separator.trim().to_string()),
+        };
+        let volume = match config.volume_pattern.as_deref().map(str::trim) {
+            Some(pattern) if !pattern.is_empty() => {
+                Some(build_pattern(pattern, CHINESE_VOLUME, ENGLISH_VOLUME)?)
+            }
+            _ => None,
+        };
+        Ok(Self { rule, volume })
+    }
+
+    fn classify(&self, trimmed: &str) -> LineKind {
+        if let ChapterRule::Separator(separator) = &self.rule {
+            if trimmed == separator {
+                return LineKind::Separator;
+            }
+        }
+        if let Some(title) = self.volume.as_ref().and_then(|re| capture_title(re, trimmed)) {
+            return LineKind::Volume(title);
+        }
+        match &self.rule {
+            ChapterRule::Heading(re) => capture_title(re, trimmed)
+                .map_or(LineKind::Content, LineKind::Heading),
+            ChapterRule::Separator(_) => LineKind::Content,
+        }
+    }
+}
+
+struct RawChapter {
+    title: String,
+    content: String,
+    volume: Option<Volume>,
+    // 稿件中指定的預約時間 (Markdown front matter)
+    schedule: Option<DateTime<Tz>>,
+    // 標題所在行號，僅文字稿有
+    line: Option<usize>,
+}
+
+#[derive(Default)]
+struct PendingChapter {
+    title: String,
+    content: String,
+    line: Option<usize>,
+}
+
+impl PendingChapter {
+    fn push_to(&mut self, chapters: &mut Vec<RawChapter>, volume: &Option<Volume>) {
+        let pending = std::mem::take(self);
+        chapters.push(RawChapter {
+            title: pending.title,
+            content: pending.content,
+            volume: volume.clone(),
+            schedule: None,
+            line: pending.line,
+        });
+    }
+}
+
+fn split_chapters(text: &str, marker: &ChapterMarker) -> Result<Vec<RawChapter>> {
+    let mut chapters = Vec::new();
+    let mut current = PendingChapter::default();
+    let mut current_volume: Option<Volume> = None;
+    let heading_mode = matches!(marker.rule, ChapterRule::Heading(_));
+    // 標題模式下，第一個標題之前的內容 (書名、簡介等) 不計入章節
+    let mut started = !heading_mode;
+
+    for (line_no, line) in text.lines().enumerate() {
+        let line_no = line_no + 1;
+        let trimmed = line.trim();
+
+        match marker.classify(trimmed) {
+            LineKind::Separator => {
+                current.push_to(&mut chapters, &current_volume);
+                continue;
+            }
+            LineKind::Volume(title) => {
+                if started && (heading_mode || !current.title.is_empty()) {
+                    current.push_to(&mut chapters, &current_volume);
+                }
+                let index = current_volume.as_ref().map_or(1, |v| v.index + 1);
+                current_volume = Some(Volume { index, title });
+                // 標題模式下，卷標題與下一章之間的內容 (卷首語) 不計入章節
+                started = !heading_mode;
+                continue;
+            }
+            LineKind::Heading(title) => {
+                if started {
+                    current.push_to(&mut chapters, &current_volume);
+                }
+                started = true;
+                current.title = title;
+                current.line = Some(line_no);
+                continue;
+            }
+            LineKind::Content if !started => continue,
+            LineKind::Content => {}
+        }
+
+        if trimmed.is_empty() && (current.title.is_empty() || current.content.is_empty()) {
+            continue;
+        }
+
+        if current.title.is_empty() {
+            current.title = trimmed.to_string();
+            current.line = Some(line_no);
+        } else {
+            current.content.push_str(line);
+            current.content.push('\n');
+        }
+    }
+
+    if !current.title.is_empty() {
+        current.push_to(&mut chapters, &current_volume);
+    }
+    Ok(chapters)
+}
+
+fn read_chapters(book_path: &str, config: &Config) -> Result<Vec<RawChapter>> {
+    if epub::is_epub(book_path) {
+        return epub::read_chapters(book_path);
+    }
+    if markdown::is_markdown_dir(book_path) {
+        return markdown::read_chapters(book_path, config.encoding.as_deref(), config.timezone()?);
+    }
+    let marker = ChapterMarker::build(config)?;
+    let text = encoding::read_text(book_path, config.encoding.as_deref())?;
+    split_chapters(&text, &marker)
+}
+// --- 結束新增 ---
+
 impl Config {
     pub fn book_id(&self) -> &str {
         &self.book_id
@@ -83,139 +318,225 @@ impl Config {
         book_path: &str,
     ) -> Result<()> {
         println!("開始同步...");
-        let file = File::open(book_path)?;
-        let reader = BufReader::new(file);
-        let mut chapters = Vec::new();
-        let mut current_chapter = Vec::new();
-        let trimmed_delimiter = self.chapter_separator.trim();
-
-        for line in reader.lines() {
-            let line = line?;
-            if line.trim() == trimmed_delimiter {
-                if !current_chapter.is_empty() {
-                    chapters.push(current_chapter);
-                    current_chapter = Vec::new();
-                }
-            } else {
-                current_chapter.push(line);
-            }
-        }
-        if !current_chapter.is_empty() {
-            chapters.push(current_chapter);
+        let chapters = self.published_titles(book_path)?;
+
+        let mut matches = matching::match_titles(hashmap, &chapters);
+        matching::print_report(&matches, &chapters);
+        if !matching::review(&mut matches, &chapters) {
+            println!("已取消同步");
+            return Ok(());
         }
 
-        let mut title_to_index = HashMap::new();
-        for (idx, chapter) in chapters.iter().enumerate() {
-            if let Some(title_line) = chapter.iter().find(|line| !line.trim().is_empty()) {
-                let title = title_line.trim().to_string();
-                title_to_index.insert(title, idx + 1);
+        let mut results: Vec<(usize, String, String)> = Vec::new();
+        for m in &matches {
+            let Some(index) = m.index() else { continue };
+            if results.iter().any(|&(existing, _, _)| existing == index) {
+                println!("警告：章節 {} 已有對應，略過遠端章節「{}」", index, m.remote_title());
+                continue;
             }
+            results.push((index, m.remote_title().to_string(), m.remote_id().to_string()));
         }
+        results.sort_by_key(|&(index, _, _)| index);
 
-        let mut results: Vec<_> = hashmap
-            .into_iter()
-            .filter_map(|(orig_title, _)| {
-                let trimmed_title = orig_title.trim().to_string();
-                title_to_index
-                    .get(&trimmed_title)
-                    .map(|&index| (index, orig_title))
-            })
-            .collect();
-        results.sort_by_key(|&(index, _)| index);
-
-        let file = File::create(&self.chapter_list)?;
-        let mut writer = BufWriter::new(file);
-        for (index, title) in results {
-            writeln!(writer, "{},{}", index, title)?;
+        // 保留仍能對應的章節原有記錄 (發布時間、雜湊等)，其餘移除
+        let mut ledger = self.ledger()?;
+        ledger.retain(|entry| results.iter().any(|&(index, _, _)| index == entry.index()));
+        for (index, title, remote_id) in results {
+            ledger.upsert(index, &title).set_remote_id(&remote_id);
         }
+        ledger.save(&self.chapter_list)?;
         println!("同步成功");
         Ok(())
     }
 
+    // 唯讀比對本地稿件、章節列表與遠端目錄 (依網站順序的 (標題, 章節 ID))，不修改任何檔案
+    pub fn diff(&self, remote: &[(String, String)], book_path: &str) -> Result<DiffReport> {
+        let chapters = self.published_titles(book_path)?;
+        Ok(diff::diff(remote, &chapters, &Ledger::read(&self.chapter_list)?))
+    }
+
+    // 已發布的標題經過簡轉繁，與遠端比對前先以相同方式轉換本地標題
+    fn published_titles(&self, book_path: &str) -> Result<Vec<RawChapter>> {
+        let mut chapters = read_chapters(book_path, self)?;
+        if let Some(converter) = self.converter()? {
+            for chapter in chapters.iter_mut() {
+                chapter.title = converter.convert(&chapter.title);
+            }
+        }
+        Ok(chapters)
+    }
+
     pub fn append_chapter(path: &Path, index: usize, title: &str) -> Result<()> {
-        let mut file = OpenOptions::new()
-            .write(true)
-            .append(true)
-            .open(path)?;
+        let mut ledger = Ledger::load(path)?;
+        ledger.upsert(index, title);
+        ledger.save(path)
+    }
+
+    pub fn load_chapter_list(&self) -> Result<Vec<(usize, String)>> {
+        Ok(self.ledger()?
+            .entries()
+            .iter()
+            .map(|entry| (entry.index(), entry.title().to_string()))
+            .collect())
+    }
+
+    pub fn ledger(&self) -> Result<Ledger> {
+        Ledger::load(&self.chapter_list)
+    }
 
-        writeln!(file, "{},{}", index, title)?;
+    pub fn set_remote_id(&self, index: usize, title: &str, remote_id: &str) -> Result<()> {
+        let mut ledger = self.ledger()?;
+        ledger.upsert(index, title).set_remote_id(remote_id);
+        ledger.save(&self.chapter_list)
+    }
+
+    // 發布後記錄每章的發布時間、預約時間、內容雜湊與加密資源；rule 為本次設定的排程規則
+    pub fn record_published(&self, chapters: &[Chapter], schedules: &[Option<ScheduleInfo>], rule: Option<ScheduleRule>) -> Result<()> {
+        let mut ledger = self.ledger()?;
+        let mut reserved = Vec::with_capacity(chapters.len());
+        let now = Utc::now().to_rfc3339();
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let scheduled_at = schedules.get(idx).cloned().flatten()
+                .map(|schedule| format!("{} {}:{}", schedule.date, schedule.hour, schedule.minute));
+            reserved.push((chapter.index, chapter.title.clone(), scheduled_at.clone()));
+            let entry = ledger.upsert(chapter.index, &chapter.title);
+            entry.set_volume(chapter.volume.as_ref().map(|volume| volume.title.as_str()));
+            entry.set_published_at(&now);
+            entry.set_scheduled_at(scheduled_at);
+            entry.set_content_hash(&chapter.content_hash);
+            entry.set_assets(chapter.font_path.as_deref(), chapter.css_url.as_deref());
+        }
+        // 記錄最後預約的時段，下次可接續排程
+        if let Some(last) = schedules.iter().flatten()
+            .map(|schedule| format!("{} {}:{}", schedule.date, schedule.hour, schedule.minute))
+            .max()
+        {
+            ledger.schedule_mut().advance(&last);
+        }
+        if let Some(rule) = rule {
+            ledger.schedule_mut().set_rule(rule);
+        }
+        ledger.save(&self.chapter_list)?;
 
+        // 記錄到跨書籍的預約記錄，供其他書籍排程時檢查衝突
+        let mut registry = Registry::load()?;
+        registry.record(&self.book_id, &reserved);
+        registry.save()?;
+
+        if let Some(path) = schedule::calendar_path()? {
+            let count = calendar::export(None, &path)?;
+            println!("已更新發布行事曆 {} ({} 個事件)", path.display(), count);
+        }
         Ok(())
     }
 
-    pub fn load_chapter_list(&self) -> Result<Vec<(usize, String)>> {
-        let file = File::open(self.chapter_list())?;
-        let reader = BufReader::new(file);
-        let mut vec = Vec::new();
-
-        for line in reader.lines() {
-            let line = line?;
-            if let Some((index_str, title)) = line.split_once(',') {
-                if let Ok(index) = index_str.trim().parse::<usize>() {
-                    vec.push((index, title.trim().to_string()));
-                } else {
-                    eprintln!("無法解析：{}", index_str);
+    pub fn record_edited(&self, chapters: &[Chapter]) -> Result<()> {
+        let mut ledger = self.ledger()?;
+        let now = Utc::now().to_rfc3339();
+        for chapter in chapters {
+            let entry = ledger.upsert(chapter.index, &chapter.title);
+            entry.set_updated_at(&now);
+            entry.set_content_hash(&chapter.content_hash);
+            entry.set_assets(chapter.font_path.as_deref(), chapter.css_url.as_deref());
+        }
+        ledger.save(&self.chapter_list)
+    }
+
+    // 依分卷整理書籍章節，供遠端目錄按卷分組
+    pub fn volumes(&self, book_path: &str) -> Result<Vec<VolumeGroup>> {
+        let mut groups: Vec<VolumeGroup> = Vec::new();
+        for (idx, chapter) in read_chapters(book_path, self)?.into_iter().enumerate() {
+            match groups.last_mut() {
+                Some((volume, items)) if *volume == chapter.volume => {
+                    items.push((idx + 1, chapter.title));
                 }
+                _ => groups.push((chapter.volume, vec![(idx + 1, chapter.title)])),
             }
         }
-        Ok(vec)
+        Ok(groups)
     }
 
 
-    pub fn build(book_path: &str) -> Result<Self> {
-        let book_name = Path::new(&book_path)
-            .file_name()
-            .ok_or(anyhow::anyhow!("無效的文件路徑"))?
-            .to_string_lossy()
-            .to_string();
+    fn converter(&self) -> Result<Option<convert::Converter>> {
+        if !self.s2t {
+            return Ok(None);
+        }
+        convert::Converter::build(self.phrase_dict.as_deref()).map(Some)
+    }
 
-        let mut config = Self::get_all()?;
+    pub fn timezone(&self) -> Result<Tz> {
+        schedule::input_timezone(self.timezone.as_deref())
+    }
 
-        if config.contains_key(&book_name) {
-            return Ok(config[&book_name].clone());
-        }
+    pub fn build(book_path: &str) -> Result<Self> {
+        let mut config = Self::find_or_register(book_path)?;
+        config.book_path = book_path.to_string();
+        Ok(config)
+    }
 
-        let book_id = get_input("請輸入書籍編號: ");
-        let forum_id = get_input("請輸入論壇編號: ");
-        let encrypt = get_input("是否加密書籍(y/n): ");
-        let encrypt = if encrypt.to_lowercase() == "y" {
-            true
-        } else {
-            false
-        };
-        let chapter_separator = get_input("請輸入章節分隔符: ");
-        let chapter_list = Path::new(CHAPTER_DIR).join(&book_id);
-        fs::create_dir_all(CHAPTER_DIR)
-            .context("無法創建目錄")?;
-        File::create(&chapter_list)
-            .context("無法創建文件")?;
-        let book_config = Config {
-            book_id,
-            forum_id,
-            encrypt,
-            chapter_separator,
-            chapter_list,
+    // 不登記也不詢問：已登記的書籍，或稿件旁的書籍設定檔，都沒有時回傳錯誤
+    pub fn find(book_path: &str) -> Result<Self> {
+        Self::find_in(&BookStore::read()?, book_path)
+    }
+
+    // 舊版遷移的檔名別名不需確認即可使用，因為這裡不會修改任何設定
+    fn find_in(store: &BookStore, book_path: &str) -> Result<Self> {
+        let registered = store.lookup(book_path)?
+            .or_else(|| store.legacy(book_path))
+            .and_then(|book_id| store.get(&book_id).cloned());
+        let mut config = match registered {
+            Some(config) => config,
+            None => match BookManifest::load(book_path)? {
+                Some(book_manifest) => manifest::preview(book_path, book_manifest)?,
+                None => return Err(anyhow!("{} 尚未登記，請先使用 book add 登記書籍或在稿件旁建立 {}",
+                                           book_path, BookManifest::path(book_path).display())),
+            },
         };
-        config.insert(book_name, book_config.clone());
-        Self::save(&config)?;
-        Ok(book_config)
+        config.book_path = book_path.to_string();
+        Ok(config)
     }
-    fn get_all() -> Result<ConfigMap> {
-        if Path::new(CONFIG_FILE).exists() {
-            let content = fs::read_to_string(CONFIG_FILE)
-                .with_context(|| format!("讀取配置文件 {} 失敗", CONFIG_FILE))?;
-            serde_json::from_str(&content)
-                .context("序列化失敗")
 
-        } else {
-            Ok(HashMap::new())
+    fn find_or_register(book_path: &str) -> Result<Self> {
+        let mut store = BookStore::load()?;
+        if let Some(config) = store.lookup(book_path)?.and_then(|book_id| store.get(&book_id).cloned()) {
+            return Ok(config);
+        }
+        if let Some(config) = store.legacy(book_path).and_then(|book_id| store.get(&book_id).cloned()) {
+            let prompt = format!("舊版設定中有同名書籍 (書籍編號 {})，是否為同一本書 (y/n，默認 y): ", config.book_id);
+            if get_input(&prompt).to_lowercase() != "n" {
+                store.link(book_path, &config.book_id)?;
+                store.save()?;
+                return Ok(config);
+            }
+        }
+
+        // 稿件旁有書籍設定檔時直接登記，不需要互動輸入
+        if let Some(book_manifest) = BookManifest::load(book_path)? {
+            println!("使用書籍設定檔 {}", BookManifest::path(book_path).display());
+            return manifest::register(book_path, book_manifest);
+        }
+
+        let book_id = prompt_id("請輸入書籍編號: ", "書籍編號");
+        // 已登記的書籍只是稿件移動或改名時，直接連結到原設定
+        if let Some(config) = store.get(&book_id).cloned() {
+            println!("書籍編號 {} 已登記，路徑: {}", book_id, store.paths(&book_id).join(", "));
+            if get_input("是否將此路徑連結至該書籍 (y/n，默認 y): ").to_lowercase() != "n" {
+                store.link(book_path, &book_id)?;
+                store.save()?;
+                return Ok(config);
+            }
         }
+        manifest::register(book_path, BookManifest::prompt(book_path, book_id))
     }
+}
 
-    fn save(config: &ConfigMap) -> Result<()> {
-        let content = serde_json::to_string_pretty(config).context("序列化失敗")?;
-        fs::write(CONFIG_FILE, content).context("寫入配置文件失敗")?;
-        Ok(())
+impl Volume {
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
     }
 }
 
@@ -240,6 +561,10 @@ impl Chapter {
         self.index
     }
 
+    pub fn volume(&self) -> Option<&Volume> {
+        self.volume.as_ref()
+    }
+
     pub fn encrypt(&self) -> bool {
         self.css_url.is_some()
     }
@@ -251,90 +576,23 @@ impl Chapter {
 
 
 // --- 新增：獲取並驗證單個初始時間的函數 ---
-fn prompt_for_initial_datetime() -> Result<chrono::DateTime<chrono_tz::Tz>> {
-    let mmdd_re = Regex::new(r"^(0[1-9]|1[0-2])(0[1-9]|[12]\d|3[01])$").unwrap();
-    let hour_re = Regex::new(r"^(0\d|1\d|2[0-3])$").unwrap();
-
-    let taipei_now = Utc::now().with_timezone(&Taipei);
-    let current_year = taipei_now.year();
-    let taipei_now_date = taipei_now.date_naive();
-    println!("將使用當前年份: {}", current_year);
-    println!("当前灣灣の台北時間: {}", taipei_now.format("%Y-%m-%d %H:%M:%S %Z"));
-
-    let naive_date: NaiveDate;
-    // MMDD 輸入與驗證循環
-    loop {
-        let input = get_input("請輸入初始預約月份和日期 (格式 MMDD): ");
-        if let Some(caps) = mmdd_re.captures(&input) {
-            let month = caps.get(1).unwrap().as_str();
-            let day = caps.get(2).unwrap().as_str();
-            let current_date_str = format!("{}-{}-{}", current_year, month, day);
-
-            if let Ok(parsed_date) = NaiveDate::parse_from_str(&current_date_str, "%Y-%m-%d") {
-                if parsed_date < taipei_now_date {
-                     println!("錯誤：日期 ({}) 不能在當前台北日期 ({}) 之前。",
-                              parsed_date.format("%Y-%m-%d"),
-                              taipei_now_date.format("%Y-%m-%d"));
-                    continue;
-                }
-                naive_date = parsed_date;
-                break;
-            } else {
-                println!("日期無效 (例如，月份 '{}' 沒有第 '{}' 天)，請重新輸入。", month, day);
-            }
-        } else {
-            println!("格式錯誤，請輸入 MMDD 格式。");
-        }
+fn prompt_for_initial_datetime(tz: Tz) -> Result<chrono::DateTime<chrono_tz::Tz>> {
+    let taipei_now = Utc::now().with_timezone(&tz);
+    println!("輸入時區: {}，當前時間: {}", tz.name(), taipei_now.format("%Y-%m-%d %H:%M:%S %Z"));
+    if tz != SITE_TIMEZONE {
+        println!("網站時區 {} 當前時間: {}", SITE_TIMEZONE.name(),
+                 taipei_now.with_timezone(&SITE_TIMEZONE).format("%Y-%m-%d %H:%M:%S %Z"));
     }
 
-    let mut hour_num: u32;
-    // 小時 輸入與驗證循環
     loop {
-        let input = get_input("請輸入初始預約小時 (00-23): ");
-         if hour_re.is_match(&input) {
-            hour_num = input.parse::<u32>().unwrap();
-            if naive_date == taipei_now_date && hour_num < taipei_now.hour() {
-                println!("錯誤：小時 ({:02}) 不能在當前台北小時 ({:02}) 之前。",
-                         hour_num, taipei_now.hour());
-                continue;
+        let input = get_input(&format!("請輸入初始預約時間 ({}): ", timespec::SPEC_HELP));
+        match timespec::parse_point(&input, tz) {
+            Ok(scheduled_dt_taipei) => {
+                // 時間有效，返回輸入時區的 DateTime
+                println!("初始預約時間設定為: {}", format_slot(&scheduled_dt_taipei));
+                return Ok(scheduled_dt_taipei); // <--- 返回計算好的初始時間點
             }
-            break;
-        } else {
-            println!("小時輸入無效。");
-        }
-    }
-
-    // 分鐘 輸入與驗證循環
-    loop {
-        let input = get_input("請輸入初始預約分鐘 (00 或 30): ");
-        if input == "00" || input == "30" {
-            let minute_num: u32 = input.parse().unwrap();
-            if let Some(naive_time) = NaiveTime::from_hms_opt(hour_num, minute_num, 0) {
-                let scheduled_naive_dt = naive_date.and_time(naive_time);
-                match Taipei.from_local_datetime(&scheduled_naive_dt).single() {
-                    Some(scheduled_dt_taipei) => {
-                        let taipei_now_for_minute_check = Utc::now().with_timezone(&Taipei);
-                        if scheduled_dt_taipei <= taipei_now_for_minute_check {
-                            println!("錯誤：初始預約時間 ({}) 不能等於或早於當前台北時間 ({})。",
-                                     scheduled_dt_taipei.format("%Y-%m-%d %H:%M %Z"),
-                                     taipei_now_for_minute_check.format("%Y-%m-%d %H:%M %Z"));
-                            continue; // 重新輸入分鐘
-                        }
-                        // 時間有效，返回 DateTime<Taipei>
-                        println!("初始預約時間設定為 (灣灣の台北時間): {}", scheduled_dt_taipei.format("%Y-%m-%d %H:%M %Z"));
-                        return Ok(scheduled_dt_taipei); // <--- 返回計算好的初始時間點
-                    }
-                    None => {
-                         println!("錯誤：無法將輸入時間明確轉換為台北時區時間。");
-                         continue;
-                    }
-                }
-            } else {
-                 println!("內部錯誤：無法創建有效時間。");
-                 continue;
-            }
-        } else {
-            println!("分鐘輸入無效，請輸入 00 或 30。");
+            Err(e) => println!("錯誤：{}", e),
         }
     }
 }
@@ -357,11 +615,12 @@ fn prompt_for_interval() -> Result<ScheduleInterval> {
             _ => println!("無效輸入，請輸入 0 到 23 之間的整數。"),
         }
     };
+    let step = i64::from(schedule::minute_step()?);
      let minutes = loop {
-        let input = get_input("  - 分鐘數 (0 或 30): ");
+        let input = get_input(&format!("  - 分鐘數 (0-59，{} 的倍數): ", step));
         match input.parse::<i64>() {
-             Ok(m) if m == 0 || m == 30 => break m,
-             _ => println!("無效輸入，請輸入 0 或 30。"),
+             Ok(m) if (0..60).contains(&m) && m % step == 0 => break m,
+             _ => println!("無效輸入，請輸入 0 到 59 之間 {} 的倍數。", step),
         }
     };
 
@@ -375,50 +634,182 @@ fn prompt_for_interval() -> Result<ScheduleInterval> {
 
 // --- 新增：計算所有章節的發布時間表 ---
 fn calculate_chapter_schedules(
+    config: &Config,
     initial_datetime: chrono::DateTime<chrono_tz::Tz>,
     interval: ScheduleInterval,
     count: usize, // 章節數量
 ) -> Result<Vec<ScheduleInfo>> {
     let interval_duration = interval.to_duration()?; // 獲取 chrono::Duration
-    let mut schedules = Vec::with_capacity(count);
+    let mut slots = Vec::with_capacity(count);
 
-    println!("計算得到的各章節預定發布時間 (台北時間):");
     for i in 0..count {
         // 計算當前章節的時間 = 初始時間 + i * 間隔
         // 使用 checked_add_signed 防止溢出
         let current_datetime = initial_datetime.checked_add_signed(interval_duration * (i as i32))
             .ok_or_else(|| anyhow!("計算第 {} 個章節時間時發生溢出", i + 1))?;
-
-        // 格式化回 ScheduleInfo 所需的字符串
-        let date_str = current_datetime.format("%Y-%m-%d").to_string();
-        let hour_str = current_datetime.format("%H").to_string();
-        // --- 重要修正：確保分鐘也是兩位數，並且處理非 00/30 的情況 (雖然這裡輸入是 00/30) ---
-        // 格式化為兩位數分鐘
-        let minute_str = current_datetime.format("%M").to_string();
-        // 可選：如果嚴格要求輸出必須是 00 或 30，可以在這裡加邏輯，但這可能與計算結果不符
-        // if minute_str != "00" && minute_str != "30" {
-        //     println!("警告：計算得到的第 {} 章分鐘為 {}，非 00 或 30。", i + 1, minute_str);
-        // }
-
-        println!("  - 章節 {}: {}", i + 1, current_datetime.format("%Y-%m-%d %H:%M"));
-
-        schedules.push(ScheduleInfo {
-            date: date_str,
-            hour: hour_str,
-            minute: minute_str, // 使用計算得到的分鐘字符串
-        });
+        slots.push(current_datetime);
     }
-    Ok(schedules)
+
+    let slots = plan_slots(config, "計算得到的各章節預定發布時間:", slots)?;
+    // 轉換為網站時區的 ScheduleInfo
+    Ok(slots.iter().map(to_schedule_info).collect())
 }
 // --- 結束新增 ---
 
+// 套用排程限制 (封鎖日期、每日上限、允許時段、其他書籍的預約) 並印出時間表，順延的章節標示原定時間與原因
+fn plan_slots(config: &Config, heading: &str, slots: Vec<DateTime<Tz>>) -> Result<Vec<DateTime<Tz>>> {
+    let plan = schedule::ScheduleConstraints::load()?
+        .with_reserved(Registry::load()?.reserved_except(&config.book_id))
+        .apply(&slots)?;
+    println!("{}", heading);
+    for (i, (planned, original)) in plan.iter().zip(&slots).enumerate() {
+        if planned.reasons().is_empty() {
+            println!("  - 章節 {}: {}", i + 1, format_slot(&planned.datetime()));
+        } else {
+            println!("  - 章節 {}: {} (原定 {}，順延原因: {})",
+                     i + 1, format_slot(&planned.datetime()), original.format("%Y-%m-%d %H:%M"), planned.reasons().join("、"));
+        }
+        if let Some(conflict) = planned.conflict() {
+            println!("    警告：{}", conflict);
+        }
+    }
+    let moved = plan.iter().filter(|planned| !planned.reasons().is_empty()).count();
+    if moved > 0 {
+        println!("共 {} 個章節因排程限制順延", moved);
+    }
+    let conflicts = plan.iter().filter(|planned| planned.conflict().is_some()).count();
+    if conflicts > 0 {
+        println!("警告：共 {} 個章節與其他書籍的預約同一時間", conflicts);
+    }
+    Ok(plan.iter().map(|planned| planned.datetime()).collect())
+}
+
+// 所有章節使用同一時間，仍需經過排程限制 (每日上限等) 檢查
+fn unified_schedules(config: &Config, datetime: DateTime<Tz>, count: usize) -> Result<Vec<Option<ScheduleInfo>>> {
+    let schedules = calculate_chapter_schedules(config, datetime, ScheduleInterval::ZERO, count)?;
+    Ok(schedules.into_iter().map(Some).collect())
+}
+
+// 只有在這裡轉換為網站時區，其餘計算與顯示都使用輸入時區
+fn to_schedule_info(datetime: &DateTime<Tz>) -> ScheduleInfo {
+    let datetime = datetime.with_timezone(&SITE_TIMEZONE);
+    ScheduleInfo {
+        date: datetime.format("%Y-%m-%d").to_string(),
+        hour: datetime.format("%H").to_string(),
+        minute: datetime.format("%M").to_string(),
+    }
+}
+
+// 依每週固定時段 (非互動的時段字串) 計算 count 個章節的預約時間
+pub fn schedules_from_pattern(config: &Config, spec: &str, count: usize) -> Result<Vec<Option<ScheduleInfo>>> {
+    pattern_schedules(config, spec, count).map(|(schedules, _)| schedules)
+}
+
+fn pattern_schedules(config: &Config, spec: &str, count: usize) -> Result<(Vec<Option<ScheduleInfo>>, WeeklyPattern)> {
+    let tz = config.timezone()?;
+    let (pattern, start) = schedule::parse_pattern_spec(spec, tz)?;
+    println!("每週時段 ({}): {}", tz.name(), pattern);
+    let occurrences = pattern.occurrences(start, count)?;
+    schedule::check_minutes(&occurrences)?;
+    let occurrences = plan_slots(config, "計算得到的各章節預定發布時間:", occurrences)?;
+    Ok((occurrences.iter().map(|datetime| Some(to_schedule_info(datetime))).collect(), pattern))
+}
+
+// 依排程表達式 (命令列 --schedule) 計算 count 個章節的預約時間，有間隔時一併回傳排程規則
+pub fn schedules_from_spec(config: &Config, spec: &str, count: usize) -> Result<(Vec<Option<ScheduleInfo>>, Option<ScheduleRule>)> {
+    let spec = timespec::parse_spec(spec, config.timezone()?)?;
+    let interval = spec.interval();
+    let schedules = calculate_chapter_schedules(config, spec.start(), interval, count)?;
+    let rule = (interval.to_duration()? > Duration::zero())
+        .then_some(ScheduleRule::Interval { days: interval.days, hours: interval.hours, minutes: interval.minutes });
+    Ok((schedules.into_iter().map(Some).collect(), rule))
+}
+
+// 有排程表達式時直接計算，否則走互動設定
+fn schedules_for(config: &Config, spec: Option<&str>, count: usize) -> Result<(Vec<Option<ScheduleInfo>>, Option<ScheduleRule>)> {
+    match spec {
+        Some(spec) => schedules_from_spec(config, spec, count),
+        None => prompt_schedules(config, count),
+    }
+}
+
+// --- 新增：稿件已指定預約時間的章節直接採用，其餘章節再走互動設定 ---
+// 排程規則於發布成功後才由 record_published 保存
+pub fn determine_schedules(config: &Config, chapters: &[Chapter], spec: Option<&str>) -> Result<(Vec<Option<ScheduleInfo>>, Option<ScheduleRule>)> {
+    let preset: Vec<(usize, DateTime<Tz>)> = chapters.iter()
+        .filter_map(|chapter| chapter.schedule.map(|datetime| (chapter.index, datetime)))
+        .collect();
+    if preset.is_empty() {
+        return schedules_for(config, spec, chapters.len());
+    }
+
+    let now = Utc::now();
+    println!("以下章節使用稿件中指定的預約時間:");
+    for &(index, datetime) in &preset {
+        if datetime <= now {
+            return Err(anyhow!("章節 {} 的預約時間 {} 已過", index, datetime.format("%Y-%m-%d %H:%M %Z")));
+        }
+        println!("  - 章節 {}: {}", index, format_slot(&datetime));
+    }
+
+    let remaining = chapters.len() - preset.len();
+    let (prompted, rule) = if remaining > 0 {
+        println!("其餘 {} 個章節未指定預約時間:", remaining);
+        schedules_for(config, spec, remaining)?
+    } else {
+        (Vec::new(), None)
+    };
+    let mut prompted = prompted.into_iter();
+
+    let schedules = chapters.iter()
+        .map(|chapter| match &chapter.schedule {
+            Some(datetime) => Some(to_schedule_info(datetime)),
+            None => prompted.next().flatten(),
+        })
+        .collect();
+    Ok((schedules, rule))
+}
+
+// 上次有排程規則時，提供接續上次最後時段的選項
+fn continue_schedule(config: &Config, chapter_count: usize) -> Result<Option<Vec<Option<ScheduleInfo>>>> {
+    let ledger = config.ledger()?;
+    let Some(state) = ledger.schedule() else {
+        return Ok(None);
+    };
+    let (Some(last), Some(rule)) = (state.last(), state.rule()) else {
+        return Ok(None);
+    };
+    let tz = config.timezone()?;
+    println!("上次預約至 {}，排程規則: {}", format_slot(&last.with_timezone(&tz)), rule);
+    if get_input("是否接續上次的排程 (y/n，默認 y): ").to_lowercase() == "n" {
+        return Ok(None);
+    }
+    let slots = plan_slots(config, "接續排程的各章節預定發布時間:", rule.next_after(last, chapter_count, tz)?)?;
+    if get_input("是否使用以上時間表 (y/n，默認 y): ").to_lowercase() == "n" {
+        return Ok(None);
+    }
+    Ok(Some(slots.iter().map(|datetime| Some(to_schedule_info(datetime))).collect()))
+}
+
+// 接續上次排程時沿用已保存的規則，不需回傳
+fn prompt_schedules(config: &Config, chapter_count: usize) -> Result<(Vec<Option<ScheduleInfo>>, Option<ScheduleRule>)> {
+    if chapter_count > 0 {
+        if let Some(schedules) = continue_schedule(config, chapter_count)? {
+            return Ok((schedules, None));
+        }
+    }
+    prompt_new_schedules(config, chapter_count)
+}
+
 // --- 新增：決定發布時間表的函數 ---
-pub fn determine_schedules(chapter_count: usize) -> Result<Vec<Option<ScheduleInfo>>> {
+fn prompt_new_schedules(config: &Config, chapter_count: usize) -> Result<(Vec<Option<ScheduleInfo>>, Option<ScheduleRule>)> {
+    let tz = config.timezone()?;
+    let mut rule = None;
     let mut schedules: Vec<Option<ScheduleInfo>> = vec![None; chapter_count]; // 初始化為都不預約
 
     if chapter_count == 0 {
         println!("沒有找到要發布的章節。");
-        return Ok(vec![]); // 返回空 Vec
+        return Ok((vec![], None)); // 返回空 Vec
     }
 
     let schedule_choice = get_input("是否需要預約發文 (y/n，默認 n): ").to_lowercase();
@@ -426,28 +817,41 @@ pub fn determine_schedules(chapter_count: usize) -> Result<Vec<Option<ScheduleIn
     if schedule_choice == "y" {
         if chapter_count > 1 {
             // --- 多章節情況 ---
-            let advanced_choice = get_input("檢測到多個章節，是否使用進階預約發文 (按固定間隔安排) (y/n，默認 n): ").to_lowercase();
-            if advanced_choice == "y" {
+            println!("檢測到多個章節，請選擇預約方式:");
+            println!("  1. 所有章節使用統一時間");
+            println!("  2. 進階預約 (按固定間隔安排)");
+            println!("  3. 每週固定時段 (例如每週一三五 20:00)");
+            let advanced_choice = get_input("請選擇 (默認 1): ");
+            if advanced_choice == "3" {
+                // --- 每週固定時段 ---
+                println!("--- 設置每週固定時段 ---");
+                loop {
+                    let spec = get_input(&format!("請輸入每週時段 ({}): ", schedule::PATTERN_HELP));
+                    match pattern_schedules(config, &spec, chapter_count) {
+                        Ok((calculated, pattern)) => {
+                            schedules = calculated;
+                            rule = Some(ScheduleRule::Weekly { pattern: pattern.to_string() });
+                            break;
+                        }
+                        Err(e) => println!("{}", e),
+                    }
+                }
+            } else if advanced_choice == "2" {
                 // --- 進階預約 ---
                 println!("--- 設置進階預約 ---");
-                let initial_datetime = prompt_for_initial_datetime()?; // 獲取初始時間點
+                let initial_datetime = prompt_for_initial_datetime(tz)?; // 獲取初始時間點
                 let interval = prompt_for_interval()?; // 獲取間隔
 
                 // --- 新增：檢查間隔是否為零 ---
                 if interval.days == 0 && interval.hours == 0 && interval.minutes == 0 {
                     // --- 間隔為零，跳轉到統一時間邏輯 ---
                     println!("檢測到時間間隔為零，將為所有章節設置統一的初始預約時間。");
-                    let single_schedule = ScheduleInfo {
-                         date: initial_datetime.format("%Y-%m-%d").to_string(),
-                         hour: initial_datetime.format("%H").to_string(),
-                         minute: initial_datetime.format("%M").to_string(),
-                    };
-                    schedules.fill(Some(single_schedule)); // 所有章節使用相同時間
-                    println!("所有 {} 個章節將預約在 {}", chapter_count, initial_datetime.format("%Y-%m-%d %H:%M %Z"));
+                    schedules = unified_schedules(config, initial_datetime, chapter_count)?; // 所有章節使用相同時間
                     // --- 跳轉邏輯結束 ---
                 } else {
                     // --- 間隔不為零，執行原計算邏輯 ---
-                    let calculated_schedules = calculate_chapter_schedules(initial_datetime, interval, chapter_count)?; // 計算時間表
+                    let calculated_schedules = calculate_chapter_schedules(config, initial_datetime, interval, chapter_count)?; // 計算時間表
+                    rule = Some(ScheduleRule::Interval { days: interval.days, hours: interval.hours, minutes: interval.minutes });
                     schedules = calculated_schedules.into_iter().map(Some).collect();
                     // --- 原計算邏輯結束 ---
                 }
@@ -456,115 +860,217 @@ pub fn determine_schedules(chapter_count: usize) -> Result<Vec<Option<ScheduleIn
             } else {
                 // --- 多章節，但使用統一時間 ---
                 println!("--- 為所有章節設置統一預約時間 ---");
-                let single_datetime = prompt_for_initial_datetime()?; // 獲取單個時間點
-                // 將 DateTime<Taipei> 轉換為 ScheduleInfo
-                let single_schedule = ScheduleInfo {
-                     date: single_datetime.format("%Y-%m-%d").to_string(),
-                     hour: single_datetime.format("%H").to_string(),
-                     minute: single_datetime.format("%M").to_string(), // 同樣格式化分鐘
-                };
-                schedules.fill(Some(single_schedule)); // 所有章節使用相同時間
-                println!("所有 {} 個章節將預約在 {}", chapter_count, single_datetime.format("%Y-%m-%d %H:%M %Z"));
+                let single_datetime = prompt_for_initial_datetime(tz)?; // 獲取單個時間點
+                // 同樣檢查排程限制與其他書籍的預約
+                schedules = unified_schedules(config, single_datetime, chapter_count)?; // 所有章節使用相同時間
             }
         } else {
             // --- 單章節情況 ---
             println!("--- 設置單章節預約時間 ---");
-             let single_datetime = prompt_for_initial_datetime()?; // 獲取單個時間點
-             let single_schedule = ScheduleInfo {
-                 date: single_datetime.format("%Y-%m-%d").to_string(),
-                 hour: single_datetime.format("%H").to_string(),
-                 minute: single_datetime.format("%M").to_string(), // 格式化分鐘
-             };
-             // 確保 schedules 向量至少有一個元素
-             if !schedules.is_empty() {
-                schedules[0] = Some(single_schedule); // 設置第一個 (也是唯一一個)
-             }
+             let single_datetime = prompt_for_initial_datetime(tz)?; // 獲取單個時間點
+             schedules = unified_schedules(config, single_datetime, chapter_count)?;
         }
     } else {
         println!("不進行預約發布，將立即發布所有章節。");
         // schedules 保持全為 None
     }
 
-    Ok(schedules) // 返回最終的時間表向量
+    Ok((schedules, rule)) // 返回最終的時間表向量與排程規則
 }
 // --- 結束替換/新增 ---
 
+fn prompt_id(prompt: &str, label: &str) -> String {
+    loop {
+        let id = get_input(prompt);
+        match manifest::validate_id(label, &id) {
+            Ok(()) => return id,
+            Err(e) => println!("{}", e),
+        }
+    }
+}
+
+// 唯讀檢查，未登記的書籍不會進入互動登記
+pub fn lint(book_path: &str) -> Result<LintReport> {
+    let config = Config::find(book_path)?;
+    lint::lint(book_path, &config)
+}
+
 pub async fn processor(work_dir: &str) -> Result<(Config, Vec<Chapter>)>{
     let book_path = get_input("請輸入書籍路徑: ");
     let config = Config::build(&book_path)?;
 
-    let selected = get_input("請輸入需要處理的章節範圍: ");
-    let selected_indices = parse_ranges(&selected)?;
-    let chapter_map =
-        parse_chapters(&book_path, &config.chapter_separator, &selected_indices, work_dir)?;
+    println!("章節範圍格式: {}", select::SELECTOR_HELP);
+    let selection = select::parse_ranges(&get_raw_input("請輸入需要處理的章節範圍: "))?;
+    let sections = read_chapters(&book_path, &config)?;
+    let selected_ranges = select::resolve_ranges(&selection, &sections, &config)?;
+    let (config, chapters, failures) = process_chapters(config, sections, &selected_ranges, work_dir).await?;
+    if !failures.is_empty() {
+        if chapters.is_empty() {
+            return Err(anyhow!("所有章節加密失敗"));
+        }
+        if get_input(&format!("是否繼續發布其餘 {} 個章節 (y/n，默認 y): ", chapters.len())).to_lowercase() == "n" {
+            return Err(anyhow!("已取消"));
+        }
+    }
+    Ok((config, chapters))
+}
+
+// 依章節編號處理指定章節，不需互動輸入 (發布佇列使用)
+pub async fn prepare(book_path: &str, indices: &[usize], work_dir: &str) -> Result<(Config, Vec<Chapter>)> {
+    let config = Config::find(book_path)?;
+    let sections = read_chapters(book_path, &config)?;
+    if let Some(index) = indices.iter().find(|&&index| index == 0 || index > sections.len()) {
+        return Err(anyhow!("稿件中沒有第 {} 章", index));
+    }
+    let ranges: Vec<(usize, usize)> = indices.iter().map(|&index| (index, index)).collect();
+    // 佇列批次的預約時間與章節一一對應，有章節失敗時整批留待下次重試
+    let (config, chapters, failures) = process_chapters(config, sections, &ranges, work_dir).await?;
+    if !failures.is_empty() {
+        return Err(anyhow!("{} 個章節加密失敗", failures.len()));
+    }
+    Ok((config, chapters))
+}
+
+// 加密失敗的章節：(章節說明, 錯誤)
+type Failures = Vec<(String, anyhow::Error)>;
+
+async fn process_chapters(
+    config: Config,
+    sections: Vec<RawChapter>,
+    selected_ranges: &[(usize, usize)],
+    work_dir: &str,
+) -> Result<(Config, Vec<Chapter>, Failures)> {
+    let mut chapter_map = parse_chapters(sections, selected_ranges, work_dir)?;
+
+    if let Some(converter) = config.converter()? {
+        convert_chapters(&mut chapter_map, &converter)?;
+    }
 
     if config.encrypt {
-        let executable = get_executable_name(EXECUTABLE_FILE);
-        let chapters = encrypt_chapters(chapter_map, &config.book_id, work_dir, &executable).await?;
-        Ok((config, chapters))
+        let settings = EncryptSettings::load()?;
+        let encryptor = settings.encryptor()?;
+        let (chapters, failures) = encrypt_chapters(chapter_map, &config.book_id, work_dir, encryptor, settings.concurrency()).await?;
+        Ok((config, chapters, failures))
     } else {
         let chapters = no_encrypt_chapters(chapter_map, work_dir)?;
-        Ok((config, chapters))
+        Ok((config, chapters, Vec::new()))
     }
 }
 
 async fn encrypt_chapters(
-    chapter_map: BTreeMap<usize, (String, PathBuf)>,
+    chapter_map: BTreeMap<usize, ChapterFile>,
     book_id: &str,
     work_dir: &str,
-    executable: &str,
-) -> Result<Vec<Chapter>> {
+    encryptor: Arc<dyn Encryptor>,
+    concurrency: usize,
+) -> Result<(Vec<Chapter>, Failures)> {
+    let total = chapter_map.len();
     let work_dir = Arc::new(work_dir.to_string());
-    let executable = Arc::new(executable.to_string());
+    // 限制同時加密的章節數，避免一次啟動大量加密程序
+    let semaphore = Arc::new(Semaphore::new(concurrency));
+    let progress = Arc::new(Progress::new("正在加密章節", chapter_map.len()));
 
     let mut tasks = Vec::new();
 
-    for (index, (title, file_path)) in chapter_map {
+    for (index, ChapterFile { title, volume, schedule, content_hash, path: file_path }) in chapter_map {
         let work_dir = Arc::clone(&work_dir);
-        let executable = Arc::clone(&executable);
+        let encryptor = Arc::clone(&encryptor);
+        let semaphore = Arc::clone(&semaphore);
+        let progress = Arc::clone(&progress);
+        let label = format!("第 {} 章 {}", index, title);
 
+        // 加密為同步運算或外部程序，放在阻塞執行緒中進行
         let task = tokio::spawn(async move {
-            let file_name = extract_file_name(&file_path)?;
-
-            let chapter = Chapter {
-                index,
-                title,
-                target_path: format!("{}/encrypted/{}.txt", work_dir, file_name),
-                font_path: Some(format!("{}/font/{}.woff", work_dir, file_name)),
-                css_url: None,
-            };
-
-            process_chapter(file_path.to_str().ok_or(anyhow!("路徑轉換失敗"))?, &executable, &chapter).await?;
+            let name = title.clone();
+            let result = async {
+                let _permit = semaphore.acquire_owned().await.context("加密工作已中止")?;
+                progress.start(&name);
+                tokio::task::spawn_blocking(move || {
+                    let file_name = extract_file_name(&file_path)?;
+
+                    let chapter = Chapter {
+                        index,
+                        title,
+                        volume,
+                        schedule,
+                        content_hash,
+                        target_path: format!("{}/encrypted/{}.txt", work_dir, file_name),
+                        font_path: Some(format!("{}/font/{}.woff", work_dir, file_name)),
+                        css_url: None,
+                    };
 
-            Ok::<_, anyhow::Error>(chapter)
+                    process_chapter(&file_path, encryptor.as_ref(), &chapter).map(|_| chapter)
+                })
+                .await
+                .context("加密工作異常結束")?
+            }
+            .await;
+            // 任何失敗 (包括取得檔名失敗) 都要計入進度
+            progress.finish(&name, result.is_ok());
+            result
         });
 
-        tasks.push(task);
+        tasks.push((label, task));
     }
 
+    // 等待所有章節完成後再回報失敗，失敗的章節略過，由呼叫端決定是否繼續
     let mut chapters = Vec::new();
-    for task in tasks {
-        let chapter = task.await??;
-        // chapter.set_css_url(&book_id).await?;
-        chapters.push(chapter);
+    let mut failures = Vec::new();
+    for (label, task) in tasks {
+        match task.await.context("加密工作異常結束").and_then(|result| result) {
+            // chapter.set_css_url(&book_id).await?;
+            Ok(chapter) => chapters.push(chapter),
+            Err(e) => failures.push((label, e)),
+        }
+    }
+    progress.close();
+
+    if !failures.is_empty() {
+        println!("以下 {} 個章節加密失敗 (共 {} 個章節):", failures.len(), total);
+        for (label, e) in &failures {
+            println!("  {}: {:#}", label, e);
+        }
     }
+    if chapters.is_empty() {
+        return Ok((chapters, failures));
+    }
+    println!("章節加密完成，成功 {} 個章節，共 {} 個章節", chapters.len(), total);
 
     Chapter::collect_css_url(book_id, &mut chapters).await?;
 
     commit::git_push()?;
 
-    Ok(chapters)
+    Ok((chapters, failures))
+}
+
+fn convert_chapters(
+    chapter_map: &mut BTreeMap<usize, ChapterFile>,
+    converter: &convert::Converter,
+) -> Result<()> {
+    println!("正在進行簡繁轉換...");
+    for chapter in chapter_map.values_mut() {
+        chapter.title = converter.convert(&chapter.title);
+        if let Some(volume) = chapter.volume.as_mut() {
+            volume.title = converter.convert(&volume.title);
+        }
+        let content = fs::read_to_string(&chapter.path).context("讀取章節文件失敗")?;
+        fs::write(&chapter.path, converter.convert(&content)).context("寫入章節文件失敗")?;
+    }
+    Ok(())
 }
 
 fn no_encrypt_chapters(
-    chapter_map: BTreeMap<usize, (String, PathBuf)>,
+    chapter_map: BTreeMap<usize, ChapterFile>,
     work_dir: &str,)
     ->Result<Vec<Chapter>> {
     let mut chapters_vec = Vec::new();
-    for (index, (title, file_path)) in chapter_map {
+    for (index, ChapterFile { title, volume, schedule, content_hash, path: file_path }) in chapter_map {
         let chapter = Chapter {
             index,
             title,
+            volume,
+            schedule,
+            content_hash,
             target_path: format!("{}/{}.txt", work_dir, extract_file_name(&file_path)?),
             font_path: None,
             css_url: None,
@@ -575,28 +1081,6 @@ fn no_encrypt_chapters(
     Ok(chapters_vec)
 }
 
-fn parse_ranges(input: &str) -> Result<Vec<(usize, usize)>> {
-    let re = Regex::new(r"^\s*(\d+(-\d*)?\s*,\s*)*\d+(-\d*)?\s*$")?;
-    if !re.is_match(input) {
-        return Err(anyhow!("章節範圍格式錯誤"));
-    }
-
-    let mut ranges = Vec::new();
-    for part in input.split(',') {
-        let part = part.trim();
-        if part.contains('-') {
-            let range: Vec<&str> = part.split('-').collect();
-            let start = range[0].parse::<usize>().unwrap_or(1);
-            let end = range.get(1)
-                .and_then(|&s| s.parse::<usize>().ok()).unwrap_or(usize::MAX);
-            ranges.push((start, end));
-        } else if let Ok(num) = part.parse::<usize>() {
-            ranges.push((num, num));
-        }
-    }
-    ranges.sort_unstable_by_key(|&(start, _)| start);
-    Ok(ranges)
-}
 fn extract_file_name(file_path: &PathBuf) -> Result<String> {
     file_path.file_stem()
         .ok_or(anyhow!("文件路徑錯誤"))
@@ -604,19 +1088,9 @@ fn extract_file_name(file_path: &PathBuf) -> Result<String> {
 }
 
 
-async fn process_chapter(file_path: &str, executable: &str, chapter: &Chapter) -> Result<()> {
-    println!("正在加密章節: {}", chapter.title);
-    let output = tokio::process::Command::new(executable)
-        .args(["-f", file_path, "-s", &chapter.target_path, "-t",
-            &chapter.font_path.as_ref().ok_or(anyhow!("字體路徑錯誤"))?])
-        .output()
-        .await
-        .context("執行加密程序失敗")?;
-    if !output.status.success() {
-        return Err(anyhow!("加密失敗: {}", String::from_utf8_lossy(&output.stderr)));
-    }
-    println!("章節加密完成: {}", chapter.title);
-    Ok(())
+fn process_chapter(file_path: &Path, encryptor: &dyn Encryptor, chapter: &Chapter) -> Result<()> {
+    let font_path = chapter.font_path.as_ref().ok_or(anyhow!("字體路徑錯誤"))?;
+    encryptor.encrypt(file_path, Path::new(&chapter.target_path), Path::new(font_path))
 }
 
 fn save_chapter(work_path: &Path, file_name: usize, content: &str) -> Result<PathBuf> {
@@ -626,86 +1100,77 @@ fn save_chapter(work_path: &Path, file_name: usize, content: &str) -> Result<Pat
     Ok(chapter_path)
 }
 
-fn finish_chapter(
-    chapter_num: &mut usize,
-    current_title: &mut String,
-    current_content: &mut String,
-    current_range: &mut Option<(usize, usize)>,
-    chapters: &mut BTreeMap<usize, (String, PathBuf)>,
-    work_path: &Path,
-    range_iter: &mut impl Iterator<Item = (usize, usize)>,
-) -> Result<()> {
-    *chapter_num += 1;
-    if let Some((start, end)) = *current_range {
-        if (start..=end).contains(&*chapter_num) {
-            let chapter_path = save_chapter(work_path, *chapter_num, current_content)?;
-            chapters.insert(*chapter_num, (current_title.clone(), chapter_path));
-        }
-        if *chapter_num >= end {
-            *current_range = range_iter.next();
-        }
-    }
-    current_title.clear();
-    current_content.clear();
-    Ok(())
+struct ChapterFile {
+    title: String,
+    volume: Option<Volume>,
+    schedule: Option<DateTime<Tz>>,
+    content_hash: String,
+    path: PathBuf,
 }
 
 fn parse_chapters(
-    book_path: &str,
-    separator: &str,
+    sections: Vec<RawChapter>,
     ranges: &[(usize, usize)],
     work_dir: &str,
-) -> Result<BTreeMap<usize, (String, PathBuf)>> {
-    let file = File::open(book_path).context("無法打開文件")?;
-    let reader = BufReader::new(file);
+) -> Result<BTreeMap<usize, ChapterFile>> {
     let work_path = Path::new(work_dir);
     let mut chapters = BTreeMap::new();
 
-    let mut chapter_num = 0;
-    let mut current_title = String::new();
-    let mut current_content = String::new();
-    let mut range_iter = ranges.iter().copied();
-    let mut current_range = range_iter.next();
+    for (idx, section) in sections.into_iter().enumerate() {
+        let chapter_num = idx + 1;
+        if ranges.iter().any(|&(start, end)| (start..=end).contains(&chapter_num)) {
+            let path = save_chapter(work_path, chapter_num, &section.content)?;
+            chapters.insert(chapter_num, ChapterFile {
+                title: section.title,
+                volume: section.volume,
+                schedule: section.schedule,
+                content_hash: ledger::content_hash(&section.content),
+                path,
+            });
+        }
+    }
+    Ok(chapters)
+}
 
-    for line in reader.lines() {
-        let line = line.context("讀取行失敗")?;
-        let trimmed = line.trim();
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        if trimmed == separator {
-            finish_chapter(
-                &mut chapter_num,
-                &mut current_title,
-                &mut current_content,
-                &mut current_range,
-                &mut chapters,
-                work_path,
-                &mut range_iter,
-            )?;
-            continue;
+    #[test]
+    fn chinese_heading_separators() {
+        let re = Regex::new(CHINESE_HEADING).unwrap();
+        for heading in ["第十二章", "第十二章 標題", "第十二章：標題", "第12章:標題", "第三回、標題", "第１２節．標題"] {
+            assert_eq!(capture_title(&re, heading).as_deref(), Some(heading));
         }
-
-        if trimmed.is_empty() && (current_title.is_empty() || current_content.is_empty()) {
-            continue;
+        // 編號後直接接其他文字的是內文，不是標題
+        for line in ["他翻到第十二章", "第二回合開始了", "第三節課下課後", "第十二章標題"] {
+            assert!(capture_title(&re, line).is_none(), "{}", line);
         }
-
-        if current_title.is_empty() {
-            current_title = trimmed.to_string();
-        } else {
-            current_content.push_str(&line);
-            current_content.push('\n');
+        let re = Regex::new(CHINESE_VOLUME).unwrap();
+        assert!(re.is_match("第一卷：開端"));
+        assert!(re.is_match("第二部 終章"));
+        assert!(re.is_match("第三卷"));
+        for line in ["第一部分的人…", "第二卷軸被打開了"] {
+            assert!(!re.is_match(line), "{}", line);
         }
     }
 
-    if !current_title.is_empty() {
-        finish_chapter(
-            &mut chapter_num,
-            &mut current_title,
-            &mut current_content,
-            &mut current_range,
-            &mut chapters,
-            work_path,
-            &mut range_iter,
-        )?;
+    #[test]
+    fn find_uses_migrated_file_name_alias() {
+        let dir = std::env::temp_dir().join("auto_esj_find_legacy");
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("book.json");
+        let legacy = r#"{"novel.txt": {"book_id": "123", "forum_id": "456", "chapter_list": "chapters/123",
+            "chapter_separator": "---", "encrypt": false}}"#;
+        fs::write(&path, legacy).unwrap();
+
+        let store = BookStore::parse_file(&path).unwrap();
+        let config = Config::find_in(&store, "elsewhere/novel.txt").unwrap();
+        assert_eq!(config.book_id(), "123");
+        assert_eq!(config.book_path, "elsewhere/novel.txt");
+        // 唯讀讀取不遷移檔案
+        assert_eq!(fs::read_to_string(&path).unwrap(), legacy);
+        assert!(Config::find_in(&store, "elsewhere/other.txt").is_err());
+        fs::remove_dir_all(&dir).unwrap();
     }
-    Ok(chapters)
 }
@@ -0,0 +1,87 @@
+use std::io::Write;
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+// 顯示的章節標題最多字數，避免進度超過一行
+const TITLE_WIDTH: usize = 20;
+
+// 批次處理的單行進度：完成數、總數、預計剩餘時間與目前處理中的章節
+pub struct Progress {
+    label: &'static str,
+    total: usize,
+    started: Instant,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    done: usize,
+    failed: usize,
+    running: Vec<String>,
+}
+
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    if seconds >= 3600 {
+        format!("{}:{:02}:{:02}", seconds / 3600, seconds % 3600 / 60, seconds % 60)
+    } else {
+        format!("{:02}:{:02}", seconds / 60, seconds % 60)
+    }
+}
+
+impl Progress {
+    pub fn new(label: &'static str, total: usize) -> Self {
+        let progress = Self { label, total, started: Instant::now(), state: Mutex::new(State::default()) };
+        progress.render(&progress.lock());
+        progress
+    }
+
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    pub fn start(&self, title: &str) {
+        let mut state = self.lock();
+        state.running.push(title.to_string());
+        self.render(&state);
+    }
+
+    pub fn finish(&self, title: &str, success: bool) {
+        let mut state = self.lock();
+        if let Some(position) = state.running.iter().position(|running| running == title) {
+            state.running.remove(position);
+        }
+        state.done += 1;
+        if !success {
+            state.failed += 1;
+        }
+        self.render(&state);
+    }
+
+    // 結束進度行，之後的輸出從新的一行開始
+    pub fn close(&self) {
+        println!();
+    }
+
+    fn render(&self, state: &State) {
+        let percent = (state.done * 100).checked_div(self.total).unwrap_or(100);
+        // 以已完成章節的平均時間估計剩餘時間
+        let eta = match state.done {
+            0 => "計算中".to_string(),
+            done => format_duration(self.started.elapsed() / done as u32 * (self.total - done) as u32),
+        };
+        let mut line = format!("\r\x1b[2K{} {}/{} ({}%)，預計剩餘 {}", self.label, state.done, self.total, percent, eta);
+        if state.failed > 0 {
+            line.push_str(&format!("，失敗 {}", state.failed));
+        }
+        if let Some(current) = state.running.last() {
+            let title: String = current.chars().take(TITLE_WIDTH).collect();
+            line.push_str(&format!("，目前: {}", title));
+            if state.running.len() > 1 {
+                line.push_str(&format!(" 等 {} 章", state.running.len()));
+            }
+        }
+        print!("{}", line);
+        let _ = std::io::stdout().flush();
+    }
+}
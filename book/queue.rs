@@ -0,0 +1,458 @@
+use crate::book::registry::Registry;
+use crate::book::schedule::{parse_slot, SITE_TIMEZONE, SLOT_FORMAT};
+use crate::book::ledger::content_hash;
+use crate::book::{read_chapters, to_schedule_info, Chapter, Config};
+use crate::esj::ScheduleInfo;
+use crate::utils::load_optional;
+use anyhow::{anyhow, Context, Result};
+use chrono::{Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::ErrorKind;
+use std::path::Path;
+use std::time::{Duration as StdDuration, Instant, SystemTime};
+
+// 本地發布佇列，取代網站預約，由 queue run 常駐程序在時間到達時發布
+pub const QUEUE_FILE: &str = "queue.json";
+// 修改佇列期間存在的鎖定檔，常駐程序與命令列不會互相覆蓋修改
+const LOCK_FILE: &str = "queue.json.lock";
+// 先寫入暫存檔再改名，程序中斷時不會留下寫到一半的 queue.json
+const TEMP_FILE: &str = "queue.json.tmp";
+const LOCK_TIMEOUT: StdDuration = StdDuration::from_secs(10);
+// 修改只需數毫秒，超過此時間的鎖定檔視為程序中斷時遺留
+const STALE_LOCK: StdDuration = StdDuration::from_secs(60);
+
+const USAGE: &str = "用法: queue list | queue run | queue pause <編號|all> | queue resume <編號|all> | queue cancel <編號|all>";
+
+// 常駐程序錯過時段 (例如程序未執行) 時的處理方式
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    // 立即補發
+    Publish,
+    // 略過，標記為已錯過
+    Skip,
+    // 暫停，等待手動 resume 或 cancel
+    #[default]
+    Pause,
+}
+
+// config.toml 的 [queue] 區段
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct QueueSettings {
+    catch_up: CatchUp,
+    // 超過預定時間在此分鐘數內仍直接發布，不套用補發規則
+    grace_minutes: i64,
+    poll_seconds: u64,
+}
+
+impl Default for QueueSettings {
+    fn default() -> Self {
+        Self { catch_up: CatchUp::default(), grace_minutes: 10, poll_seconds: 30 }
+    }
+}
+
+impl QueueSettings {
+    pub fn load() -> Result<Self> {
+        Ok(load_optional("queue")?.unwrap_or_default())
+    }
+
+    pub fn poll_interval(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.poll_seconds.max(1))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum QueueStatus {
+    Pending,
+    Paused,
+    // 常駐程序正在發布，程序中斷時會留下此狀態
+    Publishing,
+    Done,
+    Failed,
+    Missed,
+    Cancelled,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct QueueItem {
+    id: u64,
+    book_id: String,
+    book_path: String,
+    index: usize,
+    title: String,
+    // 加入佇列時的章節內容雜湊 (與章節記錄相同)，發布前用來確認仍是同一章節
+    #[serde(default)]
+    content_hash: String,
+    // 網站時區 YYYY-MM-DD HH:MM
+    slot: String,
+    status: QueueStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    message: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Queue {
+    next_id: u64,
+    items: Vec<QueueItem>,
+}
+
+// 同一書籍同時到期的章節一起發布
+pub struct QueueBatch {
+    book_path: String,
+    ids: Vec<u64>,
+    indices: Vec<usize>,
+    titles: Vec<String>,
+    hashes: Vec<String>,
+    slots: Vec<String>,
+}
+
+impl QueueBatch {
+    pub fn book_path(&self) -> &str {
+        &self.book_path
+    }
+
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ids.is_empty()
+    }
+
+    // 稿件在加入佇列後增刪章節時，同一編號可能已是另一個章節；
+    // 標題或內容與加入時不同的項目從批次中移除並回傳 (編號, 原因)
+    pub fn verify(&mut self) -> Result<Vec<(u64, String)>> {
+        let config = Config::find(&self.book_path)?;
+        let sections = read_chapters(&self.book_path, &config)?;
+        let converter = config.converter()?;
+        let mut rejected = Vec::new();
+        let mut position = 0;
+        while position < self.ids.len() {
+            let index = self.indices[position];
+            let reason = match sections.get(index.wrapping_sub(1)) {
+                None => Some(format!("稿件中沒有第 {} 章", index)),
+                Some(section) => {
+                    let title = converter.as_ref().map_or(section.title.clone(), |converter| converter.convert(&section.title));
+                    if title != self.titles[position] {
+                        Some(format!("第 {} 章的標題已變更為「{}」(加入佇列時為「{}」)", index, title, self.titles[position]))
+                    } else if !self.hashes[position].is_empty() && content_hash(&section.content) != self.hashes[position] {
+                        Some(format!("第 {} 章「{}」的內容在加入佇列後已修改", index, title))
+                    } else {
+                        None
+                    }
+                }
+            };
+            match reason {
+                Some(reason) => {
+                    rejected.push((self.ids[position], format!("{}，未發布，請重新加入佇列", reason)));
+                    self.ids.remove(position);
+                    self.indices.remove(position);
+                    self.titles.remove(position);
+                    self.hashes.remove(position);
+                    self.slots.remove(position);
+                }
+                None => position += 1,
+            }
+        }
+        Ok(rejected)
+    }
+
+    // 記錄到章節列表時使用原定時段
+    pub fn schedules(&self) -> Vec<Option<ScheduleInfo>> {
+        self.slots.iter().map(|slot| parse_slot(slot).map(|datetime| to_schedule_info(&datetime))).collect()
+    }
+}
+
+fn status_label(status: QueueStatus) -> &'static str {
+    match status {
+        QueueStatus::Pending => "等待中",
+        QueueStatus::Paused => "已暫停",
+        QueueStatus::Publishing => "發布中",
+        QueueStatus::Done => "已發布",
+        QueueStatus::Failed => "失敗",
+        QueueStatus::Missed => "已錯過",
+        QueueStatus::Cancelled => "已取消",
+    }
+}
+
+struct QueueLock;
+
+impl QueueLock {
+    // 常駐程序在 tokio 執行環境中等待，不可阻塞執行緒
+    async fn acquire() -> Result<Self> {
+        let started = Instant::now();
+        loop {
+            match OpenOptions::new().write(true).create_new(true).open(LOCK_FILE) {
+                Ok(_) => return Ok(Self),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    let stale = fs::metadata(LOCK_FILE)
+                        .and_then(|metadata| metadata.modified())
+                        .is_ok_and(|modified| {
+                            SystemTime::now().duration_since(modified).is_ok_and(|age| age > STALE_LOCK)
+                        });
+                    if stale {
+                        let _ = fs::remove_file(LOCK_FILE);
+                        continue;
+                    }
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        return Err(anyhow!("發布佇列正由其他程序修改中，若沒有其他程序請刪除 {}", LOCK_FILE));
+                    }
+                    tokio::time::sleep(StdDuration::from_millis(50)).await;
+                }
+                Err(e) => return Err(e).with_context(|| format!("無法建立鎖定檔 {}", LOCK_FILE)),
+            }
+        }
+    }
+}
+
+impl Drop for QueueLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(LOCK_FILE);
+    }
+}
+
+impl Queue {
+    // 鎖定後讀取最新的佇列、修改並寫回，所有修改都應透過此函數
+    pub async fn update<T>(change: impl FnOnce(&mut Queue) -> Result<T>) -> Result<T> {
+        let _lock = QueueLock::acquire().await?;
+        let mut queue = Self::load()?;
+        let value = change(&mut queue)?;
+        queue.save()?;
+        Ok(value)
+    }
+
+    pub fn load() -> Result<Self> {
+        if !Path::new(QUEUE_FILE).exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(QUEUE_FILE)
+            .with_context(|| format!("讀取發布佇列 {} 失敗", QUEUE_FILE))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("發布佇列 {} 格式錯誤", QUEUE_FILE))
+    }
+
+    fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("序列化失敗")?;
+        fs::write(TEMP_FILE, content)
+            .with_context(|| format!("寫入發布佇列 {} 失敗", TEMP_FILE))?;
+        fs::rename(TEMP_FILE, QUEUE_FILE)
+            .with_context(|| format!("寫入發布佇列 {} 失敗", QUEUE_FILE))
+    }
+
+    // 加入佇列，沒有預約時間的章節於下次檢查時發布；同時記錄到跨書籍預約記錄
+    pub fn enqueue(&mut self, config: &Config, chapters: &[Chapter], schedules: &[Option<ScheduleInfo>]) -> Result<usize> {
+        let now = Utc::now().with_timezone(&SITE_TIMEZONE).format(SLOT_FORMAT).to_string();
+        // 發布中的項目可能已送出，不可取代，整批不加入
+        let publishing: Vec<usize> = chapters.iter()
+            .map(|chapter| chapter.index)
+            .filter(|&index| self.items.iter().any(|item| {
+                item.book_id == config.book_id && item.index == index && item.status == QueueStatus::Publishing
+            }))
+            .collect();
+        if !publishing.is_empty() {
+            return Err(anyhow!("第 {:?} 章正在發布中，請待發布完成後再加入佇列", publishing));
+        }
+        let mut reserved = Vec::with_capacity(chapters.len());
+        for (idx, chapter) in chapters.iter().enumerate() {
+            let slot = schedules.get(idx).cloned().flatten()
+                .map_or(now.clone(), |schedule| format!("{} {}:{}", schedule.date, schedule.hour, schedule.minute));
+            // 同一章節重新加入時取代尚未發布的舊項目
+            for item in self.items.iter_mut().filter(|item| {
+                item.book_id == config.book_id && item.index == chapter.index && item.is_open()
+            }) {
+                item.status = QueueStatus::Cancelled;
+                item.message = Some("已由新的排程取代".to_string());
+            }
+            self.next_id += 1;
+            self.items.push(QueueItem {
+                id: self.next_id,
+                book_id: config.book_id.clone(),
+                book_path: config.book_path.clone(),
+                index: chapter.index,
+                title: chapter.title.clone(),
+                content_hash: chapter.content_hash.clone(),
+                slot: slot.clone(),
+                status: QueueStatus::Pending,
+                message: None,
+            });
+            reserved.push((chapter.index, chapter.title.clone(), Some(slot)));
+        }
+        let mut registry = Registry::load()?;
+        registry.record(&config.book_id, &reserved);
+        registry.save()?;
+        Ok(chapters.len())
+    }
+
+    // 常駐程序啟動時，上次中斷在發布中的項目無法確定是否已送出，改為失敗由使用者確認
+    pub fn recover(&mut self) -> usize {
+        let mut count = 0;
+        for item in self.items.iter_mut().filter(|item| item.status == QueueStatus::Publishing) {
+            item.status = QueueStatus::Failed;
+            item.message = Some("發布過程中斷，請確認網站上是否已發布".to_string());
+            count += 1;
+        }
+        count
+    }
+
+    // 取出已到期的項目並標記為發布中；錯過寬限時間的項目依補發規則處理
+    pub fn take_due(&mut self, settings: &QueueSettings) -> Vec<QueueBatch> {
+        let now = Utc::now();
+        let mut batches: Vec<QueueBatch> = Vec::new();
+        let mut due: Vec<&mut QueueItem> = self.items.iter_mut()
+            .filter(|item| item.status == QueueStatus::Pending)
+            .filter(|item| parse_slot(&item.slot).is_none_or(|slot| slot <= now))
+            .collect();
+        due.sort_by(|a, b| a.slot.cmp(&b.slot).then(a.index.cmp(&b.index)));
+
+        for item in due {
+            let late = parse_slot(&item.slot).map_or(Duration::zero(), |slot| now - slot.with_timezone(&Utc));
+            if late > Duration::minutes(settings.grace_minutes) {
+                let message = format!("錯過預定時間 {}", item.slot);
+                match settings.catch_up {
+                    CatchUp::Publish => {}
+                    CatchUp::Skip => {
+                        item.status = QueueStatus::Missed;
+                        item.message = Some(message);
+                        continue;
+                    }
+                    CatchUp::Pause => {
+                        item.status = QueueStatus::Paused;
+                        item.message = Some(message);
+                        continue;
+                    }
+                }
+            }
+            item.status = QueueStatus::Publishing;
+            match batches.iter_mut().find(|batch| batch.book_path == item.book_path) {
+                Some(batch) => {
+                    batch.ids.push(item.id);
+                    batch.indices.push(item.index);
+                    batch.titles.push(item.title.clone());
+                    batch.hashes.push(item.content_hash.clone());
+                    batch.slots.push(item.slot.clone());
+                }
+                None => batches.push(QueueBatch {
+                    book_path: item.book_path.clone(),
+                    ids: vec![item.id],
+                    indices: vec![item.index],
+                    titles: vec![item.title.clone()],
+                    hashes: vec![item.content_hash.clone()],
+                    slots: vec![item.slot.clone()],
+                }),
+            }
+        }
+        batches
+    }
+
+    pub fn finish(&mut self, batch: &QueueBatch, error: Option<String>) {
+        for item in self.items.iter_mut().filter(|item| batch.ids.contains(&item.id)) {
+            item.status = if error.is_some() { QueueStatus::Failed } else { QueueStatus::Done };
+            item.message = error.clone();
+        }
+    }
+
+    // 發布前檢查不符的項目標記為失敗
+    pub fn reject(&mut self, rejected: &[(u64, String)]) {
+        for (id, message) in rejected {
+            if let Some(item) = self.items.iter_mut().find(|item| item.id == *id) {
+                item.status = QueueStatus::Failed;
+                item.message = Some(message.clone());
+            }
+        }
+    }
+
+    fn select(&mut self, target: &str) -> Result<Vec<&mut QueueItem>> {
+        if target == "all" {
+            return Ok(self.items.iter_mut().collect());
+        }
+        let id: u64 = target.parse().map_err(|_| anyhow!("無效的佇列編號: {}", target))?;
+        let item = self.items.iter_mut()
+            .find(|item| item.id == id)
+            .ok_or_else(|| anyhow!("找不到佇列項目 {}", id))?;
+        Ok(vec![item])
+    }
+
+    fn print(&self) {
+        let items: Vec<&QueueItem> = self.items.iter()
+            .filter(|item| item.is_open() || item.status == QueueStatus::Failed)
+            .collect();
+        if items.is_empty() {
+            println!("發布佇列中沒有待處理的項目");
+            return;
+        }
+        println!("{:>4}  {:<6}  {:<16}  {:>12}  {:>6}  標題", "編號", "狀態", "預定時間", "書籍編號", "章節");
+        for item in items {
+            println!("{:>4}  {:<6}  {:<16}  {:>12}  {:>6}  {}",
+                     item.id, status_label(item.status), item.slot, item.book_id, item.index, item.title);
+            if let Some(message) = &item.message {
+                println!("      {}", message);
+            }
+        }
+    }
+}
+
+impl QueueItem {
+    // 尚未發布也未取消
+    fn is_open(&self) -> bool {
+        matches!(self.status, QueueStatus::Pending | QueueStatus::Paused | QueueStatus::Publishing)
+    }
+}
+
+// 命令列：list / pause / resume / cancel，run 由主程式負責 (需要啟動瀏覽器)
+pub async fn manage_queue(args: &[String]) -> Result<()> {
+    let target = || args.get(1).map(String::as_str).ok_or_else(|| anyhow!("缺少佇列編號\n{}", USAGE));
+    match args.first().map(String::as_str) {
+        Some("list") | None => Queue::load()?.print(),
+        Some("pause") => {
+            let count = Queue::update(|queue| {
+                let mut count = 0;
+                for item in queue.select(target()?)?.into_iter().filter(|item| item.status == QueueStatus::Pending) {
+                    item.status = QueueStatus::Paused;
+                    item.message = None;
+                    count += 1;
+                }
+                Ok(count)
+            }).await?;
+            println!("已暫停 {} 個項目", count);
+        }
+        Some("resume") => {
+            let now = Utc::now();
+            let count = Queue::update(|queue| {
+                let mut count = 0;
+                for item in queue.select(target()?)?.into_iter().filter(|item| item.status == QueueStatus::Paused) {
+                    // 已過預定時間的項目恢復後於下次檢查時立即發布
+                    if parse_slot(&item.slot).is_none_or(|slot| slot <= now) {
+                        item.slot = now.with_timezone(&SITE_TIMEZONE).format(SLOT_FORMAT).to_string();
+                    }
+                    item.status = QueueStatus::Pending;
+                    item.message = None;
+                    count += 1;
+                }
+                Ok(count)
+            }).await?;
+            println!("已恢復 {} 個項目", count);
+        }
+        Some("cancel") => {
+            let count = Queue::update(|queue| {
+                let mut registry = Registry::load()?;
+                let mut count = 0;
+                for item in queue.select(target()?)?.into_iter().filter(|item| {
+                    matches!(item.status, QueueStatus::Pending | QueueStatus::Paused)
+                }) {
+                    item.status = QueueStatus::Cancelled;
+                    item.message = None;
+                    registry.record(&item.book_id, &[(item.index, item.title.clone(), None)]);
+                    count += 1;
+                }
+                registry.save()?;
+                Ok(count)
+            }).await?;
+            println!("已取消 {} 個項目", count);
+        }
+        _ => return Err(anyhow!("{}", USAGE)),
+    }
+    Ok(())
+}
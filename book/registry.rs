@@ -0,0 +1,141 @@
+use crate::book::schedule::{format_slot, parse_slot, SITE_TIMEZONE};
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+// 所有書籍共用的預約記錄，用於檢查跨書籍的時段衝突
+pub const REGISTRY_FILE: &str = "reservations.json";
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Reservation {
+    book_id: String,
+    index: usize,
+    title: String,
+    // 網站時區 YYYY-MM-DD HH:MM
+    slot: String,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Registry {
+    reservations: Vec<Reservation>,
+}
+
+impl Reservation {
+    pub fn book_id(&self) -> &str {
+        &self.book_id
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn slot(&self) -> &str {
+        &self.slot
+    }
+
+    pub fn datetime(&self) -> Option<DateTime<Tz>> {
+        parse_slot(&self.slot)
+    }
+}
+
+impl Registry {
+    pub fn load() -> Result<Self> {
+        if !Path::new(REGISTRY_FILE).exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(REGISTRY_FILE)
+            .with_context(|| format!("讀取預約記錄 {} 失敗", REGISTRY_FILE))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("預約記錄 {} 格式錯誤", REGISTRY_FILE))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("序列化失敗")?;
+        fs::write(REGISTRY_FILE, content)
+            .with_context(|| format!("寫入預約記錄 {} 失敗", REGISTRY_FILE))
+    }
+
+    // 重新發布的章節取代舊的預約，立即發布 (沒有預約時間) 的章節只移除舊預約
+    pub fn record(&mut self, book_id: &str, chapters: &[(usize, String, Option<String>)]) {
+        self.reservations.retain(|reservation| {
+            reservation.book_id != book_id || !chapters.iter().any(|(index, _, _)| *index == reservation.index)
+        });
+        for (index, title, slot) in chapters {
+            if let Some(slot) = slot {
+                self.reservations.push(Reservation {
+                    book_id: book_id.to_string(),
+                    index: *index,
+                    title: title.clone(),
+                    slot: slot.clone(),
+                });
+            }
+        }
+        self.reservations.sort_by(|a, b| a.slot.cmp(&b.slot).then_with(|| a.book_id.cmp(&b.book_id)));
+    }
+
+    pub fn reservations(&self) -> &[Reservation] {
+        &self.reservations
+    }
+
+    // 尚未到達的預約，依時間排序
+    pub fn upcoming(&self) -> Vec<&Reservation> {
+        let now = Utc::now();
+        self.reservations.iter()
+            .filter(|reservation| reservation.datetime().is_some_and(|datetime| datetime > now))
+            .collect()
+    }
+
+    // 其他書籍尚未到達的預約時段，供排程時避開
+    pub fn reserved_except(&self, book_id: &str) -> Vec<(DateTime<Tz>, String)> {
+        self.upcoming().into_iter()
+            .filter(|reservation| reservation.book_id != book_id)
+            .filter_map(|reservation| {
+                let label = format!("書籍 {} 第 {} 章", reservation.book_id, reservation.index);
+                reservation.datetime().map(|datetime| (datetime, label))
+            })
+            .collect()
+    }
+}
+
+// 命令列：列出所有書籍 (或指定書籍) 尚未到達的預約
+pub fn reservations(args: &[String]) -> Result<()> {
+    let book_id = match args {
+        [] => None,
+        [book_id] => Some(book_id.as_str()),
+        _ => return Err(anyhow!("用法: schedule [書籍編號]")),
+    };
+    let registry = Registry::load()?;
+    let upcoming: Vec<&Reservation> = registry.upcoming().into_iter()
+        .filter(|reservation| book_id.is_none_or(|id| reservation.book_id == id))
+        .collect();
+    if upcoming.is_empty() {
+        println!("沒有尚未到達的預約");
+        return Ok(());
+    }
+
+    println!("{:<22}  {:>12}  {:>6}  標題", "預約時間 (網站時區)", "書籍編號", "章節");
+    let mut day = String::new();
+    for reservation in &upcoming {
+        let date = reservation.slot.split(' ').next().unwrap_or_default();
+        if date != day {
+            let count = upcoming.iter().filter(|other| other.slot.starts_with(date)).count();
+            println!("--- {} ({} 章) ---", date, count);
+            day = date.to_string();
+        }
+        let slot = reservation.datetime().map_or(reservation.slot.clone(), |datetime| format_slot(&datetime));
+        let collision = upcoming.iter()
+            .any(|other| other.slot == reservation.slot && other.book_id != reservation.book_id);
+        println!("{:<22}  {:>12}  {:>6}  {}{}",
+                 slot, reservation.book_id, reservation.index, reservation.title,
+                 if collision { "  (與其他書籍同一時間)" } else { "" });
+    }
+    println!("共 {} 個預約 ({})", upcoming.len(), SITE_TIMEZONE.name());
+    Ok(())
+}
@@ -0,0 +1,145 @@
+use crate::book::registry::Registry;
+use crate::book::schedule::{check_minute, format_slot, parse_slot, ScheduleConstraints};
+use crate::book::timespec::{parse_duration, parse_point};
+use crate::book::{to_schedule_info, Chapter, Config};
+use crate::esj::ScheduleInfo;
+use crate::utils::get_input;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+
+const HELP: &str = "指令:
+  s <列> <±間隔>     調整單一章節，例如 s 3 +1d、s 2 -30m
+  a <列> <±間隔>     調整該列及之後的所有章節
+  w <列> <列>        交換兩個章節的時間
+  n <列>             改為立即發布
+  t <列> <時間>      指定時間，例如 t 4 2027-01-03 20:30
+  Enter 確認發布，q 取消";
+
+fn parse_row(token: Option<&str>, count: usize) -> Result<usize> {
+    let token = token.ok_or_else(|| anyhow!("缺少列號"))?;
+    match token.parse::<usize>() {
+        Ok(row) if (1..=count).contains(&row) => Ok(row - 1),
+        _ => Err(anyhow!("列號必須在 1 到 {} 之間: {}", count, token)),
+    }
+}
+
+// +1d12h、-30m
+fn parse_offset(token: Option<&str>) -> Result<Duration> {
+    let token = token.ok_or_else(|| anyhow!("缺少調整的間隔"))?;
+    let (sign, duration) = match token.strip_prefix('-') {
+        Some(duration) => (-1, duration),
+        None => (1, token.strip_prefix('+').unwrap_or(token)),
+    };
+    Ok(parse_duration(duration)?.to_duration()? * sign)
+}
+
+// 調整後的時間不會自動順延，違反排程限制的列在下方標示警告
+fn print_table(chapters: &[Chapter], slots: &[Option<DateTime<Tz>>], constraints: &ScheduleConstraints) {
+    println!("{:>4}  {:>6}  {:<28}  預約時間", "列", "章節", "標題");
+    let warnings = constraints.check(slots);
+    for (row, ((chapter, slot), warnings)) in chapters.iter().zip(slots).zip(&warnings).enumerate() {
+        let slot = slot.as_ref().map_or("立即發布".to_string(), format_slot);
+        println!("{:>4}  {:>6}  {:<28}  {}", row + 1, chapter.index(), chapter.title(), slot);
+        if !warnings.is_empty() {
+            println!("{:>4}  警告：{}", "", warnings.join("、"));
+        }
+    }
+}
+
+// 調整後的時間需晚於現在且符合網站允許的分鐘
+fn check(slot: &DateTime<Tz>) -> Result<()> {
+    if *slot <= Utc::now() {
+        return Err(anyhow!("預約時間 {} 已過", slot.format("%Y-%m-%d %H:%M")));
+    }
+    check_minute(slot)
+}
+
+fn shift(slots: &mut [Option<DateTime<Tz>>], rows: std::ops::Range<usize>, offset: Duration) -> Result<()> {
+    let mut shifted = slots.to_vec();
+    for row in rows {
+        if let Some(slot) = shifted[row].as_mut() {
+            *slot += offset;
+            check(slot)?;
+        }
+    }
+    slots.copy_from_slice(&shifted);
+    Ok(())
+}
+
+fn apply(command: &str, slots: &mut [Option<DateTime<Tz>>], tz: Tz) -> Result<()> {
+    let count = slots.len();
+    let mut tokens = command.split_whitespace();
+    let action = tokens.next().unwrap_or_default();
+    match action {
+        "s" => {
+            let row = parse_row(tokens.next(), count)?;
+            if slots[row].is_none() {
+                return Err(anyhow!("第 {} 列沒有預約時間，請用 t 指定時間", row + 1));
+            }
+            shift(slots, row..row + 1, parse_offset(tokens.next())?)
+        }
+        "a" => {
+            let row = parse_row(tokens.next(), count)?;
+            shift(slots, row..count, parse_offset(tokens.next())?)
+        }
+        "w" => {
+            let first = parse_row(tokens.next(), count)?;
+            let second = parse_row(tokens.next(), count)?;
+            slots.swap(first, second);
+            Ok(())
+        }
+        "n" => {
+            let row = parse_row(tokens.next(), count)?;
+            slots[row] = None;
+            Ok(())
+        }
+        "t" => {
+            let row = parse_row(tokens.next(), count)?;
+            let point = tokens.collect::<Vec<_>>().join(" ");
+            slots[row] = Some(parse_point(&point, tz)?);
+            Ok(())
+        }
+        _ => Err(anyhow!("無法辨識的指令: {}\n{}", command, HELP)),
+    }
+}
+
+// 發布前逐列檢視與調整預約時間，回傳 None 表示取消發布
+pub fn review_schedules(
+    config: &Config,
+    chapters: &[Chapter],
+    schedules: Vec<Option<ScheduleInfo>>,
+) -> Result<Option<Vec<Option<ScheduleInfo>>>> {
+    let tz = config.timezone()?;
+    let mut slots = schedules.iter()
+        .map(|schedule| match schedule {
+            Some(schedule) => {
+                let slot = format!("{} {}:{}", schedule.date, schedule.hour, schedule.minute);
+                parse_slot(&slot)
+                    .map(|datetime| Some(datetime.with_timezone(&tz)))
+                    .ok_or_else(|| anyhow!("無法解析預約時間: {}", slot))
+            }
+            None => Ok(None),
+        })
+        .collect::<Result<Vec<_>>>()?;
+    slots.resize(chapters.len(), None);
+    let constraints = ScheduleConstraints::load()?
+        .with_reserved(Registry::load()?.reserved_except(config.book_id()));
+
+    println!("--- 發布前檢視時間表 ---");
+    println!("{}", HELP);
+    loop {
+        print_table(chapters, &slots, &constraints);
+        let command = get_input("請輸入指令 (Enter 確認發布): ");
+        match command.to_lowercase().as_str() {
+            "" => break,
+            "q" => return Ok(None),
+            command => {
+                if let Err(e) = apply(command, &mut slots, tz) {
+                    println!("錯誤：{}", e);
+                }
+            }
+        }
+    }
+    Ok(Some(slots.iter().map(|slot| slot.as_ref().map(to_schedule_info)).collect()))
+}
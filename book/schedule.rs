@@ -0,0 +1,726 @@
+use crate::book::ScheduleInterval;
+use crate::utils::load_optional;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Timelike, Utc, Weekday};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+// 網站使用的時區，ScheduleInfo 一律以此時區表示
+pub const SITE_TIMEZONE: Tz = chrono_tz::Asia::Taipei;
+
+// 網站預設只接受整點或半點的預約時間，可於 config.toml 的 [schedule] minute_step 調整
+const DEFAULT_MINUTE_STEP: u32 = 30;
+
+// 與章節記錄的 scheduled_at 相同格式
+pub const SLOT_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+pub const PATTERN_HELP: &str = "例如 mon,wed,fri 20:00; sat 12:00 或 週一-週五 21:30，可加 from YYYY-MM-DD 指定開始日期";
+
+// config.toml 的 [schedule] 區段
+#[derive(Deserialize, Default)]
+struct ScheduleSettings {
+    timezone: Option<String>,
+    minute_step: Option<u32>,
+    // 不發布的日期或範圍，例如 2027-01-01、2027-01-20..2027-01-26
+    #[serde(default)]
+    blackout: Vec<String>,
+    max_per_day: Option<usize>,
+    // 允許發布的時段，例如 18:00-23:30
+    #[serde(default)]
+    windows: Vec<String>,
+    #[serde(default)]
+    conflict: ConflictPolicy,
+    // 發布後自動更新的行事曆檔案 (所有書籍)
+    calendar: Option<PathBuf>,
+}
+
+// 與其他書籍的預約落在同一時間時：avoid 順延到下一個時段，warn 只提示
+#[derive(Deserialize, Default, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum ConflictPolicy {
+    #[default]
+    Avoid,
+    Warn,
+}
+
+impl ScheduleSettings {
+    // 未設定 [schedule] 區段時全部使用預設值，設定值格式錯誤時回傳錯誤
+    fn load() -> Result<Self> {
+        Ok(load_optional("schedule")?.unwrap_or_default())
+    }
+
+    fn minute_step(&self) -> Result<u32> {
+        let step = self.minute_step.unwrap_or(DEFAULT_MINUTE_STEP);
+        if step == 0 || 60 % step != 0 {
+            return Err(anyhow!("minute_step 必須能整除 60: {}", step));
+        }
+        Ok(step)
+    }
+}
+
+// 網站允許的預約分鐘間隔 (以網站時區計)
+pub fn minute_step() -> Result<u32> {
+    ScheduleSettings::load()?.minute_step()
+}
+
+pub fn calendar_path() -> Result<Option<PathBuf>> {
+    Ok(ScheduleSettings::load()?.calendar)
+}
+
+// 網站時區的 YYYY-MM-DD HH:MM
+pub fn parse_slot(slot: &str) -> Option<DateTime<Tz>> {
+    let naive = NaiveDateTime::parse_from_str(slot, SLOT_FORMAT).ok()?;
+    SITE_TIMEZONE.from_local_datetime(&naive).single()
+}
+
+// 換算成網站時區後檢查分鐘是否符合網站允許的間隔；
+// 輸入時區與網站時區相差非整點時，輸入時間的分鐘無法直接判斷，一律在此檢查
+pub fn check_minute(datetime: &DateTime<Tz>) -> Result<()> {
+    check_step(datetime, minute_step()?)
+}
+
+pub fn check_minutes(datetimes: &[DateTime<Tz>]) -> Result<()> {
+    let step = minute_step()?;
+    datetimes.iter().try_for_each(|datetime| check_step(datetime, step))
+}
+
+pub fn check_step(datetime: &DateTime<Tz>, step: u32) -> Result<()> {
+    let site = datetime.with_timezone(&SITE_TIMEZONE);
+    if !site.minute().is_multiple_of(step) {
+        return Err(anyhow!("網站時間 {} 的分鐘必須是 {} 的倍數", site.format("%Y-%m-%d %H:%M"), step));
+    }
+    Ok(())
+}
+
+pub fn parse_timezone(name: &str) -> Result<Tz> {
+    name.trim().parse::<Tz>().map_err(|_| anyhow!("無效的時區: {} (例如 Asia/Taipei、America/New_York)", name))
+}
+
+// 輸入時區：書籍設定優先，其次為全域設定，都未設定時與網站時區相同
+pub fn input_timezone(book_timezone: Option<&str>) -> Result<Tz> {
+    if let Some(name) = book_timezone {
+        return parse_timezone(name);
+    }
+    match ScheduleSettings::load()?.timezone {
+        Some(name) => parse_timezone(&name),
+        None => Ok(SITE_TIMEZONE),
+    }
+}
+
+// 預覽用：輸入時區與網站時區不同時同時顯示兩者
+pub fn format_slot(datetime: &DateTime<Tz>) -> String {
+    let local = datetime.format("%Y-%m-%d %H:%M (%a)");
+    if datetime.timezone() == SITE_TIMEZONE {
+        return local.to_string();
+    }
+    let site = datetime.with_timezone(&SITE_TIMEZONE);
+    format!("{} {} / 網站 {}", local, datetime.timezone().name(), site.format("%Y-%m-%d %H:%M"))
+}
+
+// 每週固定時段，依 (星期, 時間) 排序
+#[derive(Debug, Clone, PartialEq)]
+pub struct WeeklyPattern {
+    slots: Vec<(Weekday, NaiveTime)>,
+}
+
+fn parse_weekday(name: &str) -> Option<Weekday> {
+    let name = name.trim().to_lowercase();
+    let name = name.trim_start_matches("星期").trim_start_matches('週').trim_start_matches('周');
+    match name {
+        "mon" | "monday" | "一" | "1" => Some(Weekday::Mon),
+        "tue" | "tuesday" | "二" | "2" => Some(Weekday::Tue),
+        "wed" | "wednesday" | "三" | "3" => Some(Weekday::Wed),
+        "thu" | "thursday" | "四" | "4" => Some(Weekday::Thu),
+        "fri" | "friday" | "五" | "5" => Some(Weekday::Fri),
+        "sat" | "saturday" | "六" | "6" => Some(Weekday::Sat),
+        "sun" | "sunday" | "日" | "天" | "7" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+fn weekday_label(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "mon",
+        Weekday::Tue => "tue",
+        Weekday::Wed => "wed",
+        Weekday::Thu => "thu",
+        Weekday::Fri => "fri",
+        Weekday::Sat => "sat",
+        Weekday::Sun => "sun",
+    }
+}
+
+// mon,wed,fri 或 mon-fri
+fn parse_weekdays(token: &str) -> Result<Vec<Weekday>> {
+    let mut weekdays = Vec::new();
+    for part in token.split([',', '、']).filter(|part| !part.trim().is_empty()) {
+        let invalid = || anyhow!("無法辨識的星期: {}", part);
+        match part.split_once('-') {
+            Some((from, to)) => {
+                let from = parse_weekday(from).ok_or_else(invalid)?;
+                let to = parse_weekday(to).ok_or_else(invalid)?;
+                let mut day = from;
+                loop {
+                    weekdays.push(day);
+                    if day == to {
+                        break;
+                    }
+                    day = day.succ();
+                }
+            }
+            None => weekdays.push(parse_weekday(part).ok_or_else(invalid)?),
+        }
+    }
+    Ok(weekdays)
+}
+
+// 分鐘間隔在產生時段後以網站時區檢查 (check_minutes)
+pub fn parse_time(token: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(token.trim(), "%H:%M")
+        .map_err(|_| anyhow!("時間格式錯誤 (應為 HH:MM): {}", token))
+}
+
+impl WeeklyPattern {
+    // 以分號分隔多組「星期 時間」，例如 mon,wed,fri 20:00; sat 12:00
+    pub fn parse(spec: &str) -> Result<Self> {
+        let mut slots = Vec::new();
+        for group in spec.split([';', '；']).map(str::trim).filter(|group| !group.is_empty()) {
+            let mut weekdays = Vec::new();
+            let mut times = Vec::new();
+            for token in group.split_whitespace() {
+                if token.contains(':') {
+                    for time in token.split(',').filter(|time| !time.is_empty()) {
+                        times.push(parse_time(time)?);
+                    }
+                } else {
+                    weekdays.extend(parse_weekdays(token)?);
+                }
+            }
+            if weekdays.is_empty() || times.is_empty() {
+                return Err(anyhow!("時段「{}」需要同時指定星期與時間", group));
+            }
+            for weekday in &weekdays {
+                for time in &times {
+                    slots.push((*weekday, *time));
+                }
+            }
+        }
+        if slots.is_empty() {
+            return Err(anyhow!("沒有指定任何每週時段"));
+        }
+        slots.sort_by_key(|(weekday, time)| (weekday.num_days_from_monday(), *time));
+        slots.dedup();
+        Ok(Self { slots })
+    }
+
+    // 依開始時間 (不含) 之後的時段依序產生 count 個預約時間，時段以 after 的時區解讀
+    pub fn occurrences(&self, after: DateTime<Tz>, count: usize) -> Result<Vec<DateTime<Tz>>> {
+        let tz = after.timezone();
+        let mut result = Vec::with_capacity(count);
+        let mut date = after.date_naive();
+        while result.len() < count {
+            for (_, time) in self.slots.iter().filter(|(weekday, _)| *weekday == date.weekday()) {
+                let datetime = tz.from_local_datetime(&date.and_time(*time))
+                    .earliest()
+                    .ok_or_else(|| anyhow!("{} {} 在 {} 時區不存在", date, time, tz.name()))?;
+                if datetime > after && result.len() < count {
+                    result.push(datetime);
+                }
+            }
+            date = date.succ_opt().ok_or_else(|| anyhow!("計算預約日期時發生溢出"))?;
+        }
+        Ok(result)
+    }
+}
+
+impl std::fmt::Display for WeeklyPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let groups: Vec<String> = self.slots.iter()
+            .map(|(weekday, time)| format!("{} {}", weekday_label(*weekday), time.format("%H:%M")))
+            .collect();
+        write!(f, "{}", groups.join("; "))
+    }
+}
+
+// 「時段 [from YYYY-MM-DD]」，未指定開始日期時從現在開始
+pub fn parse_pattern_spec(spec: &str, tz: Tz) -> Result<(WeeklyPattern, DateTime<Tz>)> {
+    parse_pattern_spec_at(spec, Utc::now().with_timezone(&tz))
+}
+
+fn parse_pattern_spec_at(spec: &str, now: DateTime<Tz>) -> Result<(WeeklyPattern, DateTime<Tz>)> {
+    let tz = now.timezone();
+    let (slots, start) = match spec.rsplit_once("from") {
+        Some((slots, date)) => {
+            let date = NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+                .map_err(|_| anyhow!("開始日期格式錯誤 (應為 YYYY-MM-DD): {}", date.trim()))?;
+            // 開始日期當天 00:00 的時段也包含在內
+            let start = tz.from_local_datetime(&date.and_time(NaiveTime::MIN))
+                .earliest()
+                .ok_or_else(|| anyhow!("{} 在 {} 時區不存在", date, tz.name()))?
+                - Duration::minutes(1);
+            (slots, start.max(now))
+        }
+        None => (spec, now),
+    };
+    Ok((WeeklyPattern::parse(slots)?, start))
+}
+
+// 排程限制，日期與時段以輸入時區解讀；每日上限包含其他書籍的預約
+pub struct ScheduleConstraints {
+    blackouts: Vec<(NaiveDate, NaiveDate)>,
+    max_per_day: Option<usize>,
+    windows: Vec<(NaiveTime, NaiveTime)>,
+    conflict: ConflictPolicy,
+    // 順延時每次前進的間隔，即 minute_step
+    step: Duration,
+    // 其他書籍已預約的時段與說明
+    reserved: Vec<(DateTime<Tz>, String)>,
+}
+
+pub struct PlannedSlot {
+    datetime: DateTime<Tz>,
+    // 順延原因，未順延時為空
+    reasons: Vec<String>,
+    // warn 模式下與其他書籍衝突的說明
+    conflict: Option<String>,
+}
+
+impl PlannedSlot {
+    pub fn datetime(&self) -> DateTime<Tz> {
+        self.datetime
+    }
+
+    pub fn reasons(&self) -> &[String] {
+        &self.reasons
+    }
+
+    pub fn conflict(&self) -> Option<&str> {
+        self.conflict.as_deref()
+    }
+}
+
+// 限制無法滿足時避免無限順延
+const MAX_ROLL_FORWARD: usize = 10000;
+
+fn parse_blackout(value: &str) -> Result<(NaiveDate, NaiveDate)> {
+    let date = |text: &str| NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+        .map_err(|_| anyhow!("封鎖日期格式錯誤 (應為 YYYY-MM-DD 或 YYYY-MM-DD..YYYY-MM-DD): {}", value));
+    let (from, to) = match value.split_once("..").or_else(|| value.split_once('~')) {
+        Some((from, to)) => (date(from)?, date(to)?),
+        None => (date(value)?, date(value)?),
+    };
+    if from > to {
+        return Err(anyhow!("封鎖日期範圍的開始晚於結束: {}", value));
+    }
+    Ok((from, to))
+}
+
+// 時段邊界不需對齊 minute_step (例如 09:00-23:59)，
+// 但順延到時段開始時需落在有效的預約分鐘，因此開始時間向後對齊
+fn parse_window(value: &str, step: u32) -> Result<(NaiveTime, NaiveTime)> {
+    let invalid = || anyhow!("允許時段格式錯誤 (應為 HH:MM-HH:MM): {}", value);
+    let (from, to) = value.split_once('-').ok_or_else(invalid)?;
+    let time = |text: &str| NaiveTime::parse_from_str(text.trim(), "%H:%M").map_err(|_| invalid());
+    let (from, to) = (time(from)?, time(to)?);
+    if from > to {
+        return Err(anyhow!("允許時段不可跨越午夜: {}", value));
+    }
+    let offset = (step - from.minute() % step) % step;
+    let from = from.overflowing_add_signed(Duration::minutes(i64::from(offset))).0;
+    if from > to {
+        return Err(anyhow!("允許時段內沒有符合 minute_step {} 的時間: {}", step, value));
+    }
+    Ok((from, to))
+}
+
+impl ScheduleConstraints {
+    pub fn load() -> Result<Self> {
+        let settings = ScheduleSettings::load()?;
+        let step = settings.minute_step()?;
+        let mut windows = settings.windows.iter()
+            .map(|window| parse_window(window, step))
+            .collect::<Result<Vec<_>>>()?;
+        windows.sort();
+        if settings.max_per_day == Some(0) {
+            return Err(anyhow!("max_per_day 必須大於 0"));
+        }
+        Ok(Self {
+            blackouts: settings.blackout.iter().map(|value| parse_blackout(value)).collect::<Result<_>>()?,
+            max_per_day: settings.max_per_day,
+            windows,
+            conflict: settings.conflict,
+            step: Duration::minutes(i64::from(step)),
+            reserved: Vec::new(),
+        })
+    }
+
+    pub fn with_reserved(mut self, reserved: Vec<(DateTime<Tz>, String)>) -> Self {
+        self.reserved = reserved;
+        self
+    }
+
+    fn blackout(&self, date: NaiveDate) -> Option<String> {
+        self.blackouts.iter()
+            .find(|(from, to)| (*from..=*to).contains(&date))
+            .map(|(from, to)| match from == to {
+                true => format!("封鎖日期 {}", from),
+                false => format!("封鎖日期 {}..{}", from, to),
+            })
+    }
+
+    fn window_label(&self) -> String {
+        let windows: Vec<String> = self.windows.iter()
+            .map(|(from, to)| format!("{}-{}", from.format("%H:%M"), to.format("%H:%M")))
+            .collect();
+        windows.join(", ")
+    }
+
+    // 當天第一個允許時段的開始時間
+    fn day_start(&self) -> NaiveTime {
+        self.windows.first().map_or(NaiveTime::MIN, |(from, _)| *from)
+    }
+
+    fn in_window(&self, time: NaiveTime) -> bool {
+        self.windows.is_empty() || self.windows.iter().any(|(from, to)| (*from..=*to).contains(&time))
+    }
+
+    // 同一天稍後的下一個允許時段，沒有則需順延到隔天
+    fn next_window(&self, time: NaiveTime) -> Option<NaiveTime> {
+        self.windows.iter().map(|(from, _)| *from).find(|from| *from > time)
+    }
+
+    // 只檢查不順延，回傳每個時段違反的限制，供手動調整的時間表提示
+    pub fn check(&self, slots: &[Option<DateTime<Tz>>]) -> Vec<Vec<String>> {
+        let mut per_day: HashMap<NaiveDate, usize> = HashMap::new();
+        if let Some(first) = slots.iter().flatten().next() {
+            for (reserved, _) in &self.reserved {
+                *per_day.entry(reserved.with_timezone(&first.timezone()).date_naive()).or_insert(0) += 1;
+            }
+        }
+        slots.iter()
+            .map(|slot| {
+                let Some(slot) = slot else {
+                    return Vec::new();
+                };
+                let mut warnings = Vec::new();
+                let date = slot.date_naive();
+                warnings.extend(self.blackout(date));
+                let count = per_day.entry(date).or_insert(0);
+                *count += 1;
+                if let Some(max) = self.max_per_day.filter(|max| *count > *max) {
+                    warnings.push(format!("當天超過上限 {} 章", max));
+                }
+                if !self.in_window(slot.time()) {
+                    warnings.push(format!("不在允許時段 {}", self.window_label()));
+                }
+                if let Some((_, label)) = self.reserved.iter().find(|(reserved, _)| reserved == slot) {
+                    warnings.push(format!("與{}同一時間", label));
+                }
+                warnings
+            })
+            .collect()
+    }
+
+    // 依序將違反限制的時段順延到下一個有效時段，並保持章節順序
+    pub fn apply(&self, slots: &[DateTime<Tz>]) -> Result<Vec<PlannedSlot>> {
+        let mut result: Vec<PlannedSlot> = Vec::with_capacity(slots.len());
+        let mut per_day: HashMap<NaiveDate, usize> = HashMap::new();
+        if let Some(first) = slots.first() {
+            for (reserved, _) in &self.reserved {
+                *per_day.entry(reserved.with_timezone(&first.timezone()).date_naive()).or_insert(0) += 1;
+            }
+        }
+        let step = self.step;
+        for (i, slot) in slots.iter().enumerate() {
+            let tz = slot.timezone();
+            let mut reasons = Vec::new();
+            // 前一章節被順延到此時段或之後時，從它的下一個時段開始找；
+            // 未被順延的同一時間 (間隔為零) 則維持同一時間
+            let mut current = match result.last() {
+                Some(previous) if previous.datetime >= *slot && previous.datetime != slots[i - 1] => {
+                    reasons.push("前一章節已順延".to_string());
+                    previous.datetime + step
+                }
+                Some(previous) if previous.datetime > *slot => {
+                    reasons.push("前一章節已順延".to_string());
+                    previous.datetime
+                }
+                _ => *slot,
+            }
+            .naive_local();
+
+            let mut attempts = 0;
+            let (datetime, conflict) = loop {
+                attempts += 1;
+                if attempts > MAX_ROLL_FORWARD {
+                    return Err(anyhow!("無法在排程限制內找到 {} 之後的有效時段", slot.format("%Y-%m-%d %H:%M")));
+                }
+                let date = current.date();
+                let next_day = || -> Result<NaiveDateTime> {
+                    let date = date.succ_opt().ok_or_else(|| anyhow!("計算預約日期時發生溢出"))?;
+                    Ok(date.and_time(self.day_start()))
+                };
+                let mut push = |reason: String| {
+                    if !reasons.contains(&reason) {
+                        reasons.push(reason);
+                    }
+                };
+                if let Some(reason) = self.blackout(date) {
+                    push(reason);
+                    current = next_day()?;
+                    continue;
+                }
+                if let Some(max) = self.max_per_day {
+                    if per_day.get(&date).copied().unwrap_or(0) >= max {
+                        push(format!("當天已達上限 {} 章", max));
+                        current = next_day()?;
+                        continue;
+                    }
+                }
+                if !self.in_window(current.time()) {
+                    push(format!("不在允許時段 {}", self.window_label()));
+                    current = match self.next_window(current.time()) {
+                        Some(time) => date.and_time(time),
+                        None => next_day()?,
+                    };
+                    continue;
+                }
+                // 夏令時間跳過的時刻
+                let Some(datetime) = tz.from_local_datetime(&current).earliest() else {
+                    current += step;
+                    continue;
+                };
+                // 順延的章節不可落在本書較早章節已使用的時段
+                if datetime != *slot && result.iter().any(|planned| planned.datetime == datetime) {
+                    push("與本書前面的章節同一時間".to_string());
+                    current += step;
+                    continue;
+                }
+                let conflict = self.reserved.iter()
+                    .find(|(reserved, _)| *reserved == datetime)
+                    .map(|(_, label)| format!("與{}同一時間", label));
+                match conflict {
+                    Some(reason) if self.conflict == ConflictPolicy::Avoid => {
+                        push(reason);
+                        current += step;
+                    }
+                    conflict => break (datetime, conflict),
+                }
+            };
+            *per_day.entry(datetime.date_naive()).or_insert(0) += 1;
+            if datetime == *slot {
+                reasons.clear();
+            }
+            result.push(PlannedSlot { datetime, reasons, conflict });
+        }
+        Ok(result)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ScheduleRule {
+    Interval { days: i64, hours: i64, minutes: i64 },
+    Weekly { pattern: String },
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ScheduleState {
+    // 最後一個已預約的時段，網站時區 YYYY-MM-DD HH:MM
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    last: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    rule: Option<ScheduleRule>,
+}
+
+impl ScheduleRule {
+    // 接在 last 之後的 count 個時段 (以輸入時區 tz 表示)，已過的時段會略過
+    pub fn next_after(&self, last: DateTime<Tz>, count: usize, tz: Tz) -> Result<Vec<DateTime<Tz>>> {
+        let last = last.with_timezone(&tz);
+        let now = Utc::now().with_timezone(&tz);
+        match *self {
+            ScheduleRule::Weekly { ref pattern } => {
+                let slots = WeeklyPattern::parse(pattern)?.occurrences(last.max(now), count)?;
+                check_minutes(&slots)?;
+                Ok(slots)
+            }
+            ScheduleRule::Interval { days, hours, minutes } => {
+                let step = ScheduleInterval { days, hours, minutes }.to_duration()?;
+                if step <= Duration::zero() {
+                    return Err(anyhow!("時間間隔為零，無法接續排程"));
+                }
+                let mut next = last + step;
+                while next <= now {
+                    next += step;
+                }
+                Ok((0..count).map(|i| next + step * i as i32).collect())
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ScheduleRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ScheduleRule::Interval { days, hours, minutes } => {
+                write!(f, "每隔 {} 天 {} 小時 {} 分鐘", days, hours, minutes)
+            }
+            ScheduleRule::Weekly { pattern } => write!(f, "每週 {}", pattern),
+        }
+    }
+}
+
+impl ScheduleState {
+    pub fn last(&self) -> Option<DateTime<Tz>> {
+        parse_slot(self.last.as_deref()?)
+    }
+
+    pub fn rule(&self) -> Option<&ScheduleRule> {
+        self.rule.as_ref()
+    }
+
+    // 只會往後推進，補發較早的章節不影響接續位置
+    pub fn advance(&mut self, slot: &str) {
+        if self.last.as_deref().is_none_or(|last| slot > last) {
+            self.last = Some(slot.to_string());
+        }
+    }
+
+    pub fn set_rule(&mut self, rule: ScheduleRule) {
+        self.rule = Some(rule);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn constraints(max_per_day: Option<usize>, windows: &[&str]) -> ScheduleConstraints {
+        ScheduleConstraints {
+            blackouts: Vec::new(),
+            max_per_day,
+            windows: windows.iter().map(|window| parse_window(window, 30).unwrap()).collect(),
+            conflict: ConflictPolicy::Avoid,
+            step: Duration::minutes(30),
+            reserved: Vec::new(),
+        }
+    }
+
+    fn slots(start: &str, hours: i64, count: i32) -> Vec<DateTime<Tz>> {
+        let start = parse_slot(start).unwrap();
+        (0..count).map(|i| start + Duration::hours(hours) * i).collect()
+    }
+
+    fn planned(plan: &[PlannedSlot]) -> Vec<String> {
+        plan.iter().map(|slot| slot.datetime().format(SLOT_FORMAT).to_string()).collect()
+    }
+
+    fn formatted(slots: &[DateTime<Tz>]) -> Vec<String> {
+        slots.iter().map(|slot| slot.format(SLOT_FORMAT).to_string()).collect()
+    }
+
+    #[test]
+    fn rolled_chapters_do_not_share_a_slot() {
+        let plan = constraints(Some(2), &[]).apply(&slots("2030-01-01 00:00", 8, 4)).unwrap();
+        assert_eq!(planned(&plan), [
+            "2030-01-01 00:00", "2030-01-01 08:00", "2030-01-02 00:00", "2030-01-02 00:30",
+        ]);
+    }
+
+    #[test]
+    fn rolled_chapters_fill_the_next_window() {
+        let plan = constraints(None, &["18:00-23:30"]).apply(&slots("2030-01-01 23:00", 1, 4)).unwrap();
+        assert_eq!(planned(&plan), [
+            "2030-01-01 23:00", "2030-01-02 18:00", "2030-01-02 18:30", "2030-01-02 19:00",
+        ]);
+    }
+
+    #[test]
+    fn same_time_chapters_stay_together() {
+        let plan = constraints(None, &[]).apply(&slots("2030-01-01 20:00", 0, 3)).unwrap();
+        assert_eq!(planned(&plan), ["2030-01-01 20:00"; 3]);
+    }
+
+    #[test]
+    fn window_bounds_need_not_match_minute_step() {
+        let time = |text: &str| NaiveTime::parse_from_str(text, "%H:%M").unwrap();
+        assert_eq!(parse_window("09:00-23:59", 30).unwrap(), (time("09:00"), time("23:59")));
+        assert_eq!(parse_window("09:10-12:00", 30).unwrap(), (time("09:30"), time("12:00")));
+        assert!(parse_window("09:10-09:20", 30).is_err());
+        assert!(parse_window("22:00-02:00", 30).is_err());
+    }
+
+    #[test]
+    fn rolled_chapters_land_on_the_step() {
+        let mut constraints = constraints(None, &["09:00-23:59"]);
+        constraints.step = Duration::minutes(15);
+        let plan = constraints.apply(&slots("2030-01-01 23:45", 0, 2)).unwrap();
+        assert_eq!(planned(&plan), ["2030-01-01 23:45"; 2]);
+        let plan = constraints.apply(&[parse_slot("2030-01-01 23:45").unwrap(), parse_slot("2030-01-01 23:30").unwrap()]).unwrap();
+        assert_eq!(planned(&plan), ["2030-01-01 23:45", "2030-01-02 09:00"]);
+    }
+
+    #[test]
+    fn check_reports_without_moving() {
+        let mut constraints = constraints(Some(1), &["18:00-22:00"]);
+        constraints.blackouts.push(parse_blackout("2030-01-03").unwrap());
+        let reserved = parse_slot("2030-01-02 20:00").unwrap();
+        let constraints = constraints.with_reserved(vec![(reserved, "書籍 1 第 5 章".to_string())]);
+        let slots: Vec<_> = ["2030-01-01 20:00", "2030-01-01 23:00", "2030-01-02 20:00", "2030-01-03 20:00"]
+            .iter()
+            .map(|slot| parse_slot(slot))
+            .chain([None])
+            .collect();
+        assert_eq!(constraints.check(&slots), [
+            vec![],
+            vec!["當天超過上限 1 章".to_string(), "不在允許時段 18:00-22:00".to_string()],
+            vec!["當天超過上限 1 章".to_string(), "與書籍 1 第 5 章同一時間".to_string()],
+            vec!["封鎖日期 2030-01-03".to_string()],
+            vec![],
+        ]);
+    }
+
+    #[test]
+    fn pattern_spec_with_start_date() {
+        let now = parse_slot("2030-01-01 12:00").unwrap();
+        let (pattern, start) = parse_pattern_spec_at("mon,wed 20:00; sat 12:00,18:00 from 2030-01-07", now).unwrap();
+        assert_eq!(pattern.to_string(), "mon 20:00; wed 20:00; sat 12:00; sat 18:00");
+        let occurrences = pattern.occurrences(start, 5).unwrap();
+        assert_eq!(formatted(&occurrences), [
+            "2030-01-07 20:00", "2030-01-09 20:00", "2030-01-12 12:00", "2030-01-12 18:00", "2030-01-14 20:00",
+        ]);
+    }
+
+    #[test]
+    fn pattern_spec_starts_now_without_date() {
+        // 2030-01-01 為週二
+        let now = parse_slot("2030-01-01 12:00").unwrap();
+        let (pattern, start) = parse_pattern_spec_at("tue 12:00,20:00", now).unwrap();
+        assert_eq!(start, now);
+        assert_eq!(formatted(&pattern.occurrences(start, 2).unwrap()), ["2030-01-01 20:00", "2030-01-08 12:00"]);
+        // 過去的開始日期以現在為準
+        let (_, start) = parse_pattern_spec_at("tue 20:00 from 2029-12-01", now).unwrap();
+        assert_eq!(start, now);
+    }
+
+    #[test]
+    fn pattern_minutes_are_checked_in_site_timezone() {
+        // 加德滿都 (UTC+5:45) 的 20:15 為網站時間 22:30
+        let now = parse_slot("2030-01-01 12:00").unwrap().with_timezone(&chrono_tz::Asia::Kathmandu);
+        let (pattern, start) = parse_pattern_spec_at("mon 20:15", now).unwrap();
+        let slots = pattern.occurrences(start, 2).unwrap();
+        assert!(slots.iter().all(|slot| check_step(slot, 30).is_ok()));
+        let (pattern, start) = parse_pattern_spec_at("mon 20:00", now).unwrap();
+        assert!(check_step(&pattern.occurrences(start, 1).unwrap()[0], 30).is_err());
+    }
+
+    #[test]
+    fn pattern_spec_errors() {
+        let now = parse_slot("2030-01-01 12:00").unwrap();
+        assert!(parse_pattern_spec_at("20:00", now).is_err());
+        assert!(parse_pattern_spec_at("mon", now).is_err());
+        assert!(parse_pattern_spec_at("mon 20:00 from 2030/01/07", now).is_err());
+    }
+}
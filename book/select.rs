@@ -0,0 +1,322 @@
+use crate::book::{ledger, Config, Ledger, RawChapter};
+use crate::utils::get_input;
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, NaiveDate};
+use chrono_tz::Tz;
+use regex::Regex;
+use std::collections::BTreeSet;
+
+pub const SELECTOR_HELP: &str = "N、N-M、N-、vK:N-M、last:N、unpublished、since:YYYY-MM-DD、changed、\"標題*\"，以逗號分隔，前加 ! 表示排除";
+
+enum ChapterRange {
+    Absolute(usize, usize),
+    // 卷內相對範圍：(卷號, 起始, 結束)
+    Volume(usize, usize, usize),
+    // 稿件最後 N 章
+    Last(usize),
+    // 編號大於章節記錄中最大編號的章節
+    Unpublished,
+    // 章節記錄中於該日 (含) 之後發布的章節
+    Since(NaiveDate),
+    // 內容雜湊與章節記錄不同的已發布章節
+    Changed,
+    Title(Regex),
+}
+
+// (原始輸入, 選擇器)
+pub struct Selection {
+    include: Vec<(String, ChapterRange)>,
+    exclude: Vec<(String, ChapterRange)>,
+}
+
+fn parse_span(span: &str) -> Option<(usize, usize)> {
+    match span.split_once('-') {
+        Some((start, end)) => {
+            let start = start.trim().parse::<usize>().ok()?;
+            let end = match end.trim() {
+                "" => usize::MAX,
+                end => end.parse::<usize>().ok()?,
+            };
+            Some((start, end))
+        }
+        None => span.parse::<usize>().ok().map(|num| (num, num)),
+    }
+}
+
+// 以逗號分隔，但引號內的逗號屬於標題
+fn split_selectors(input: &str) -> Result<Vec<&str>> {
+    let mut parts = Vec::new();
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in input.char_indices() {
+        match c {
+            '"' => quoted = !quoted,
+            ',' if !quoted => {
+                parts.push(input[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    if quoted {
+        return Err(anyhow!("章節範圍的引號不成對: {}", input));
+    }
+    parts.push(input[start..].trim());
+    Ok(parts)
+}
+
+// 標題萬用字元：* 任意字元，? 單一字元
+fn glob_to_regex(pattern: &str) -> Result<Regex> {
+    let mut re = String::from("^");
+    for c in pattern.chars() {
+        match c {
+            '*' => re.push_str(".*"),
+            '?' => re.push('.'),
+            c => re.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    re.push('$');
+    Regex::new(&re).with_context(|| format!("標題格式錯誤: {}", pattern))
+}
+
+fn parse_selector(part: &str) -> Result<ChapterRange> {
+    let invalid = || anyhow!("章節範圍格式錯誤: {}", part);
+    if part.starts_with('"') {
+        return glob_to_regex(part.trim_matches('"')).map(ChapterRange::Title);
+    }
+    let lower = part.to_lowercase();
+    if lower == "unpublished" {
+        return Ok(ChapterRange::Unpublished);
+    }
+    if lower == "changed" {
+        return Ok(ChapterRange::Changed);
+    }
+    if let Some(count) = lower.strip_prefix("last:") {
+        return count.trim().parse::<usize>().map(ChapterRange::Last).map_err(|_| invalid());
+    }
+    if let Some(date) = lower.strip_prefix("since:") {
+        return NaiveDate::parse_from_str(date.trim(), "%Y-%m-%d")
+            .map(ChapterRange::Since)
+            .map_err(|_| invalid());
+    }
+    if let Some(rest) = part.strip_prefix(['v', 'V']).filter(|rest| rest.starts_with(|c: char| c.is_ascii_digit())) {
+        let (volume, span) = match rest.split_once(':') {
+            Some((volume, span)) => (volume, parse_span(span.trim())),
+            None => (rest, Some((1, usize::MAX))),
+        };
+        return volume.trim().parse::<usize>().ok().zip(span)
+            .map(|(volume, (start, end))| ChapterRange::Volume(volume, start, end))
+            .ok_or_else(invalid);
+    }
+    if let Some((start, end)) = parse_span(part) {
+        return Ok(ChapterRange::Absolute(start, end));
+    }
+    // 標題必須加引號，避免拼錯的關鍵字被當成標題
+    Err(invalid())
+}
+
+pub fn parse_ranges(input: &str) -> Result<Selection> {
+    let mut selection = Selection { include: Vec::new(), exclude: Vec::new() };
+    for part in split_selectors(input)? {
+        match part.strip_prefix('!') {
+            Some(rest) => selection.exclude.push((rest.trim().to_string(), parse_selector(rest.trim())?)),
+            None => selection.include.push((part.to_string(), parse_selector(part)?)),
+        }
+    }
+    Ok(selection)
+}
+
+// 各章在所屬卷中的位置 (卷號, 卷內序號)
+fn volume_positions(sections: &[RawChapter]) -> Vec<Option<(usize, usize)>> {
+    let mut counts = std::collections::HashMap::new();
+    sections.iter()
+        .map(|section| {
+            section.volume.as_ref().map(|volume| {
+                let count = counts.entry(volume.index).or_insert(0);
+                *count += 1;
+                (volume.index, *count)
+            })
+        })
+        .collect()
+}
+
+fn range_indices(
+    range: &ChapterRange,
+    sections: &[RawChapter],
+    config: &Config,
+    ledger: &Ledger,
+    tz: Tz,
+) -> Result<Vec<usize>> {
+    let total = sections.len();
+    let indices = match range {
+        ChapterRange::Absolute(start, end) => (*start.max(&1)..=*end.min(&total)).collect(),
+        ChapterRange::Volume(volume, start, end) => volume_positions(sections)
+            .into_iter()
+            .enumerate()
+            .filter(|(_, position)| {
+                position.is_some_and(|(index, count)| index == *volume && (*start..=*end).contains(&count))
+            })
+            .map(|(idx, _)| idx + 1)
+            .collect(),
+        ChapterRange::Last(count) => (total.saturating_sub(*count) + 1..=total).collect(),
+        ChapterRange::Unpublished => {
+            let published = ledger.entries().iter().map(|entry| entry.index()).max().unwrap_or(0);
+            (published + 1..=total).collect()
+        }
+        ChapterRange::Since(date) => ledger.entries()
+            .iter()
+            .filter(|entry| {
+                entry.published_at()
+                    .and_then(|time| DateTime::parse_from_rfc3339(time).ok())
+                    .is_some_and(|time| time.with_timezone(&tz).date_naive() >= *date)
+            })
+            .map(|entry| entry.index())
+            .filter(|&index| (1..=total).contains(&index))
+            .collect(),
+        ChapterRange::Changed => select_changed(config, sections)?,
+        ChapterRange::Title(re) => sections.iter()
+            .enumerate()
+            .filter(|(_, section)| re.is_match(section.title.trim()))
+            .map(|(idx, _)| idx + 1)
+            .collect(),
+    };
+    Ok(indices)
+}
+
+// 將選擇器換算為全書章節編號的連續範圍；只有排除條件時從全書開始排除
+pub fn resolve_ranges(
+    selection: &Selection,
+    sections: &[RawChapter],
+    config: &Config,
+) -> Result<Vec<(usize, usize)>> {
+    let ledger = config.ledger()?;
+    let tz = config.timezone()?;
+    let mut selected: BTreeSet<usize> = if selection.include.is_empty() {
+        (1..=sections.len()).collect()
+    } else {
+        BTreeSet::new()
+    };
+    for (label, range) in &selection.include {
+        let indices = range_indices(range, sections, config, &ledger, tz)?;
+        if indices.is_empty() {
+            println!("警告：「{}」沒有符合的章節", label);
+        }
+        selected.extend(indices);
+    }
+    for (_, range) in &selection.exclude {
+        for index in range_indices(range, sections, config, &ledger, tz)? {
+            selected.remove(&index);
+        }
+    }
+    if selected.is_empty() {
+        return Err(anyhow!("沒有選擇任何章節"));
+    }
+    Ok(collapse_ranges(selected))
+}
+
+// 比對稿件與章節記錄中的內容雜湊，列出變更預覽並確認
+fn select_changed(config: &Config, sections: &[RawChapter]) -> Result<Vec<usize>> {
+    let ledger = config.ledger()?;
+    let mut changed = Vec::new();
+    let mut unknown = Vec::new();
+    for entry in ledger.entries() {
+        let Some(section) = entry.index().checked_sub(1).and_then(|idx| sections.get(idx)) else {
+            continue;
+        };
+        match entry.content_hash() {
+            Some(hash) if hash != ledger::content_hash(&section.content) => {
+                changed.push((entry.index(), section));
+            }
+            Some(_) => {}
+            None => unknown.push(entry.index()),
+        }
+    }
+
+    if !unknown.is_empty() {
+        println!("以下章節沒有內容雜湊記錄，無法判斷是否變更: {:?}", unknown);
+    }
+    if changed.is_empty() {
+        return Err(anyhow!("沒有偵測到內容變更的章節"));
+    }
+    println!("偵測到 {} 個章節內容有變更:", changed.len());
+    for (index, section) in &changed {
+        let count = section.content.chars().filter(|c| !c.is_whitespace()).count();
+        println!("  - 章節 {}: {} ({} 字)", index, section.title, count);
+    }
+    if get_input("是否處理以上章節 (y/n，默認 y): ").to_lowercase() == "n" {
+        return Err(anyhow!("已取消"));
+    }
+    Ok(changed.into_iter().map(|(index, _)| index).collect())
+}
+
+pub fn collapse_ranges(indices: impl IntoIterator<Item = usize>) -> Vec<(usize, usize)> {
+    let mut ranges: Vec<(usize, usize)> = Vec::new();
+    for index in indices {
+        match ranges.last_mut() {
+            Some((_, end)) if *end + 1 == index => *end = index,
+            _ => ranges.push((index, index)),
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn title_matches(range: &ChapterRange, title: &str) -> bool {
+        matches!(range, ChapterRange::Title(re) if re.is_match(title))
+    }
+
+    #[test]
+    fn quoted_title_followed_by_span() {
+        let selection = parse_ranges("\"番外*\",1-3").unwrap();
+        assert_eq!(selection.include.len(), 2);
+        assert!(title_matches(&selection.include[0].1, "番外一"));
+        assert!(matches!(selection.include[1].1, ChapterRange::Absolute(1, 3)));
+    }
+
+    #[test]
+    fn two_quoted_titles() {
+        let selection = parse_ranges("\"番外*\",\"外傳*\"").unwrap();
+        assert_eq!(selection.include.len(), 2);
+        assert!(title_matches(&selection.include[0].1, "番外一"));
+        assert!(title_matches(&selection.include[1].1, "外傳：前篇"));
+        assert!(!title_matches(&selection.include[1].1, "番外一"));
+    }
+
+    #[test]
+    fn comma_inside_quotes_belongs_to_title() {
+        let selection = parse_ranges("\"第一章,序\",!2").unwrap();
+        assert_eq!(selection.include.len(), 1);
+        assert!(title_matches(&selection.include[0].1, "第一章,序"));
+        assert!(matches!(selection.exclude[0].1, ChapterRange::Absolute(2, 2)));
+    }
+
+    #[test]
+    fn unbalanced_quotes_are_rejected() {
+        assert!(parse_ranges("番外*\",1-3").is_err());
+    }
+
+    #[test]
+    fn selector_keywords() {
+        let selection = parse_ranges("v2:3-,last:5,unpublished,since:2027-01-03,changed,10-").unwrap();
+        let ranges: Vec<_> = selection.include.iter().map(|(_, range)| range).collect();
+        assert!(matches!(ranges[0], ChapterRange::Volume(2, 3, usize::MAX)));
+        assert!(matches!(ranges[1], ChapterRange::Last(5)));
+        assert!(matches!(ranges[2], ChapterRange::Unpublished));
+        assert!(matches!(ranges[3], ChapterRange::Since(date) if *date == NaiveDate::from_ymd_opt(2027, 1, 3).unwrap()));
+        assert!(matches!(ranges[4], ChapterRange::Changed));
+        assert!(matches!(ranges[5], ChapterRange::Absolute(10, usize::MAX)));
+        assert!(parse_ranges("last:x").is_err());
+        assert!(parse_ranges("3x").is_err());
+    }
+
+    #[test]
+    fn unquoted_titles_are_rejected() {
+        for input in ["unpublised", "lats:5", "番外*", "第二章"] {
+            let error = parse_ranges(input).err().map(|e| e.to_string());
+            assert!(error.is_some_and(|e| e.starts_with("章節範圍格式錯誤")), "{}", input);
+        }
+    }
+}
@@ -0,0 +1,173 @@
+use crate::book::{Config, CONFIG_FILE};
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+
+const STORE_VERSION: u32 = 2;
+
+// book.json：書籍設定以書籍編號為鍵，稿件路徑經由別名表對應到書籍編號
+#[derive(Serialize, Deserialize)]
+pub struct BookStore {
+    version: u32,
+    books: BTreeMap<String, Config>,
+    // 稿件絕對路徑 -> 書籍編號；舊版遷移而來的別名只有檔名
+    aliases: BTreeMap<String, String>,
+}
+
+impl Default for BookStore {
+    fn default() -> Self {
+        Self { version: STORE_VERSION, books: BTreeMap::new(), aliases: BTreeMap::new() }
+    }
+}
+
+// 同一稿件不論以相對或絕對路徑輸入都對應到同一個鍵
+pub fn path_key(book_path: &str) -> Result<String> {
+    let path = Path::new(book_path);
+    let absolute = fs::canonicalize(path)
+        .or_else(|_| std::path::absolute(path))
+        .with_context(|| format!("無效的文件路徑: {}", book_path))?;
+    Ok(absolute.to_string_lossy().to_string())
+}
+
+fn file_name(book_path: &str) -> Option<String> {
+    Path::new(book_path).file_name().map(|name| name.to_string_lossy().to_string())
+}
+
+impl BookStore {
+    // 舊版以檔名為鍵的 book.json 會自動遷移並保留備份
+    pub fn load() -> Result<Self> {
+        let (store, legacy) = Self::parse(Path::new(CONFIG_FILE))?;
+        if legacy {
+            let backup = format!("{}.v1.bak", CONFIG_FILE);
+            fs::copy(CONFIG_FILE, &backup).context("備份舊配置文件失敗")?;
+            store.save()?;
+            println!("已將 {} 遷移至以書籍編號為鍵的新格式，舊文件備份於 {}", CONFIG_FILE, backup);
+        }
+        Ok(store)
+    }
+
+    // 唯讀讀取，舊版格式只在記憶體中遷移，不修改檔案 (唯讀命令使用)
+    pub fn read() -> Result<Self> {
+        Self::parse(Path::new(CONFIG_FILE)).map(|(store, _)| store)
+    }
+
+    // (書籍設定, 是否為舊版以檔名為鍵的格式)
+    fn parse(path: &Path) -> Result<(Self, bool)> {
+        if !path.exists() {
+            return Ok((Self::default(), false));
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("讀取配置文件 {} 失敗", path.display()))?;
+        let value: serde_json::Value = serde_json::from_str(&content)
+            .context("序列化失敗")?;
+        if value.get("version").is_some() {
+            return Ok((serde_json::from_value(value).context("序列化失敗")?, false));
+        }
+
+        let legacy: HashMap<String, Config> = serde_json::from_value(value)
+            .context("序列化失敗")?;
+        let mut store = Self::default();
+        for (name, config) in legacy {
+            if store.books.contains_key(&config.book_id) {
+                println!("警告：{} 與其他書籍使用相同的書籍編號 {}，將共用同一份設定", name, config.book_id);
+            }
+            store.aliases.insert(name, config.book_id.clone());
+            store.books.insert(config.book_id.clone(), config);
+        }
+        Ok((store, true))
+    }
+
+    #[cfg(test)]
+    pub(crate) fn parse_file(path: &Path) -> Result<Self> {
+        Self::parse(path).map(|(store, _)| store)
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let content = serde_json::to_string_pretty(self).context("序列化失敗")?;
+        fs::write(CONFIG_FILE, content).context("寫入配置文件失敗")?;
+        Ok(())
+    }
+
+    pub fn books(&self) -> &BTreeMap<String, Config> {
+        &self.books
+    }
+
+    pub fn get(&self, book_id: &str) -> Option<&Config> {
+        self.books.get(book_id)
+    }
+
+    // 指向該書籍的所有路徑
+    pub fn paths(&self, book_id: &str) -> Vec<&str> {
+        self.aliases.iter()
+            .filter(|(_, id)| *id == book_id)
+            .map(|(path, _)| path.as_str())
+            .collect()
+    }
+
+    pub fn lookup(&self, book_path: &str) -> Result<Option<String>> {
+        Ok(self.aliases.get(&path_key(book_path)?).cloned())
+    }
+
+    // 舊版遷移的別名只有檔名，不同資料夾的同名稿件都會符合，需由呼叫端確認
+    pub fn legacy(&self, book_path: &str) -> Option<String> {
+        file_name(book_path).and_then(|name| self.aliases.get(&name).cloned())
+    }
+
+    // 書籍編號或稿件路徑
+    pub fn resolve(&self, target: &str) -> Result<String> {
+        if self.books.contains_key(target) {
+            return Ok(target.to_string());
+        }
+        self.lookup(target)?
+            .or_else(|| self.legacy(target))
+            .filter(|book_id| self.books.contains_key(book_id))
+            .ok_or_else(|| anyhow!("找不到書籍 {}", target))
+    }
+
+    pub fn insert(&mut self, config: Config) {
+        self.books.insert(config.book_id.clone(), config);
+    }
+
+    // 連結完整路徑後，同一書籍的舊版檔名別名不再需要
+    pub fn link(&mut self, book_path: &str, book_id: &str) -> Result<()> {
+        if let Some(name) = file_name(book_path) {
+            if self.aliases.get(&name).is_some_and(|id| id == book_id) {
+                self.aliases.remove(&name);
+            }
+        }
+        self.aliases.insert(path_key(book_path)?, book_id.to_string());
+        Ok(())
+    }
+
+    // 書籍編號變更時一併更新別名
+    pub fn rekey(&mut self, old_id: &str, config: Config) {
+        self.books.remove(old_id);
+        for id in self.aliases.values_mut().filter(|id| *id == old_id) {
+            *id = config.book_id.clone();
+        }
+        self.insert(config);
+    }
+
+    pub fn remove(&mut self, book_id: &str) -> Option<Config> {
+        self.aliases.retain(|_, id| id != book_id);
+        self.books.remove(book_id)
+    }
+
+    // 稿件移動或改名後，移除舊路徑的別名並改指向新路徑
+    pub fn relink(&mut self, book_id: &str, old_path: Option<&str>, new_path: &str) -> Result<()> {
+        if !self.books.contains_key(book_id) {
+            return Err(anyhow!("找不到書籍 {}", book_id));
+        }
+        if let Some(old_path) = old_path {
+            let keys = [Some(path_key(old_path)?), file_name(old_path)];
+            for key in keys.into_iter().flatten() {
+                if self.aliases.get(&key).is_some_and(|id| id == book_id) {
+                    self.aliases.remove(&key);
+                }
+            }
+        }
+        self.link(new_path, book_id)
+    }
+}
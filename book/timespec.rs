@@ -0,0 +1,244 @@
+use crate::book::schedule::{check_step, minute_step};
+use crate::book::ScheduleInterval;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use regex::Regex;
+
+pub const SPEC_HELP: &str = "例如 2027-01-03 20:30、01-03 20:30、tomorrow 21:30、+2d 09:00、+3h，多章節可用 every 1d12h from 2026-12-30 22:00";
+
+// 排程表達式：起始時間與章節間隔，間隔為零表示所有章節使用同一時間
+pub struct ScheduleSpec {
+    start: DateTime<Tz>,
+    interval: ScheduleInterval,
+}
+
+impl ScheduleSpec {
+    pub fn start(&self) -> DateTime<Tz> {
+        self.start
+    }
+
+    pub fn interval(&self) -> ScheduleInterval {
+        self.interval
+    }
+}
+
+// 1d12h、90m、2h30m，超過 60 分鐘或 24 小時會進位
+pub fn parse_duration(text: &str) -> Result<ScheduleInterval> {
+    let re = Regex::new(r"^(?:(\d{1,6})d)?(?:(\d{1,6})h)?(?:(\d{1,6})m)?$").unwrap();
+    let text = text.trim();
+    let caps = re.captures(text)
+        .filter(|_| !text.is_empty())
+        .ok_or_else(|| anyhow!("時間間隔格式錯誤 (例如 1d12h、90m): {}", text))?;
+    let value = |i| caps.get(i).map_or(0, |m| m.as_str().parse::<i64>().unwrap_or(0));
+    let total = value(1) * 24 * 60 + value(2) * 60 + value(3);
+    Ok(ScheduleInterval { days: total / (24 * 60), hours: total % (24 * 60) / 60, minutes: total % 60 })
+}
+
+fn parse_clock(token: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(token, "%H:%M")
+        .map_err(|_| anyhow!("時間格式錯誤 (應為 HH:MM): {}", token))
+}
+
+fn relative_day(token: &str) -> Option<i64> {
+    match token {
+        "today" | "今天" => Some(0),
+        "tomorrow" | "明天" => Some(1),
+        "後天" | "后天" => Some(2),
+        _ => None,
+    }
+}
+
+// 完整日期，或省略年份的 MM-DD / MMDD (取今天或之後最近的一天，跨年時自動使用明年)
+fn parse_date(token: &str, today: NaiveDate) -> Option<NaiveDate> {
+    for format in ["%Y-%m-%d", "%Y/%m/%d"] {
+        if let Ok(date) = NaiveDate::parse_from_str(token, format) {
+            return Some(date);
+        }
+    }
+    let re = Regex::new(r"^(\d{1,2})[-/](\d{1,2})$|^(\d{2})(\d{2})$").unwrap();
+    let caps = re.captures(token)?;
+    let month: u32 = caps.get(1).or(caps.get(3))?.as_str().parse().ok()?;
+    let day: u32 = caps.get(2).or(caps.get(4))?.as_str().parse().ok()?;
+    // 2 月 29 日最多需要往後找 4 年
+    (0..=4).find_map(|offset| {
+        NaiveDate::from_ymd_opt(today.year() + offset, month, day).filter(|date| *date >= today)
+    })
+}
+
+fn local(tz: Tz, date: NaiveDate, time: NaiveTime) -> Result<DateTime<Tz>> {
+    tz.from_local_datetime(&date.and_time(time))
+        .single()
+        .ok_or_else(|| anyhow!("{} {} 在 {} 時區不存在或不明確", date, time.format("%H:%M"), tz.name()))
+}
+
+// 相對時間未指定時刻時，進位到網站允許的下一個分鐘
+fn round_up(datetime: DateTime<Tz>, step: u32) -> Result<DateTime<Tz>> {
+    let step = i64::from(step) * 60;
+    let timestamp = (datetime.timestamp() + step - 1).div_euclid(step) * step;
+    DateTime::from_timestamp(timestamp, 0)
+        .map(|utc| utc.with_timezone(&datetime.timezone()))
+        .ok_or_else(|| anyhow!("計算預約時間時發生溢出"))
+}
+
+// 單一時間點，以輸入時區解讀
+pub fn parse_point(text: &str, tz: Tz) -> Result<DateTime<Tz>> {
+    parse_point_at(text, Utc::now().with_timezone(&tz), minute_step()?)
+}
+
+fn parse_point_at(text: &str, now: DateTime<Tz>, step: u32) -> Result<DateTime<Tz>> {
+    let tz = now.timezone();
+    let today = now.date_naive();
+    let text = text.trim().to_lowercase();
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+    let datetime = match tokens.as_slice() {
+        [relative] if relative.starts_with('+') => {
+            round_up(now + parse_duration(&relative[1..])?.to_duration()?, step)?
+        }
+        [relative, time] if relative.starts_with('+') => {
+            let interval = parse_duration(&relative[1..])?;
+            if interval.hours != 0 || interval.minutes != 0 {
+                return Err(anyhow!("指定時刻時相對時間只能以天為單位: {}", text));
+            }
+            local(tz, today + Duration::days(interval.days), parse_clock(time)?)?
+        }
+        // 只有時刻：今天尚未經過則為今天，否則為明天
+        [time] if time.contains(':') => {
+            let time = parse_clock(time)?;
+            let date = if today.and_time(time) > now.naive_local() { today } else { today + Duration::days(1) };
+            local(tz, date, time)?
+        }
+        [day, time] => {
+            let date = match relative_day(day) {
+                Some(offset) => today + Duration::days(offset),
+                None => parse_date(day, today).ok_or_else(|| anyhow!("無法辨識的日期: {}", day))?,
+            };
+            local(tz, date, parse_clock(time)?)?
+        }
+        _ => return Err(anyhow!("無法辨識的時間: {} ({})", text, SPEC_HELP)),
+    };
+    if datetime <= now {
+        return Err(anyhow!("預約時間 {} 已過", datetime.format("%Y-%m-%d %H:%M %Z")));
+    }
+    check_step(&datetime, step)?;
+    Ok(datetime)
+}
+
+// 「時間點」、「時間點 every 間隔」或「every 間隔 from 時間點」
+pub fn parse_spec(spec: &str, tz: Tz) -> Result<ScheduleSpec> {
+    parse_spec_at(spec, Utc::now().with_timezone(&tz), minute_step()?)
+}
+
+fn parse_spec_at(spec: &str, now: DateTime<Tz>, step: u32) -> Result<ScheduleSpec> {
+    let spec = spec.trim().to_lowercase();
+    let (point, every) = match spec.strip_prefix("every ") {
+        Some(rest) => {
+            let (every, point) = rest.split_once(" from ")
+                .ok_or_else(|| anyhow!("every 需要以 from 指定開始時間: {}", spec))?;
+            (point, Some(every))
+        }
+        None => match spec.split_once(" every ") {
+            Some((point, every)) => (point, Some(every)),
+            None => (spec.as_str(), None),
+        },
+    };
+
+    let start = parse_point_at(point, now, step)?;
+    let interval = match every {
+        Some(every) => {
+            let interval = parse_duration(every)?;
+            let total = interval.to_duration()?.num_minutes();
+            if total == 0 {
+                return Err(anyhow!("時間間隔不能為零: {}", every.trim()));
+            }
+            if total % i64::from(step) != 0 {
+                return Err(anyhow!("時間間隔必須是 {} 分鐘的倍數: {}", step, every.trim()));
+            }
+            interval
+        }
+        None => ScheduleInterval::ZERO,
+    };
+    Ok(ScheduleSpec { start, interval })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono_tz::Asia::Taipei;
+
+    // 預設的 minute_step
+    const STEP: u32 = 30;
+
+    fn now() -> DateTime<Tz> {
+        Taipei.with_ymd_and_hms(2026, 12, 29, 10, 0, 0).unwrap()
+    }
+
+    fn point(text: &str) -> Result<DateTime<Tz>> {
+        parse_point_at(text, now(), STEP)
+    }
+
+    fn spec(text: &str) -> Result<ScheduleSpec> {
+        parse_spec_at(text, now(), STEP)
+    }
+
+    fn at(date: &str, time: &str) -> DateTime<Tz> {
+        let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").unwrap();
+        local(Taipei, date, parse_clock(time).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn absolute_point() {
+        assert_eq!(point("2027-01-03 20:30").unwrap(), at("2027-01-03", "20:30"));
+        assert_eq!(point("01-03 20:30").unwrap(), at("2027-01-03", "20:30"));
+    }
+
+    #[test]
+    fn minute_off_the_default_step_is_rejected() {
+        // 預設 minute_step = 30，網站只接受整點或半點，20:15 需調整 minute_step 才能使用
+        let error = point("2027-01-03 20:15").unwrap_err();
+        assert!(error.to_string().contains("30 的倍數"), "{}", error);
+        assert!(parse_point_at("2027-01-03 20:15", now(), 15).is_ok());
+    }
+
+    #[test]
+    fn minute_step_is_checked_in_site_timezone() {
+        // 加德滿都 (UTC+5:45) 的 20:15 為網站時間 22:30，20:00 則為 22:15
+        let now = now().with_timezone(&chrono_tz::Asia::Kathmandu);
+        assert!(parse_point_at("2027-01-03 20:15", now, STEP).is_ok());
+        assert!(parse_point_at("2027-01-03 20:00", now, STEP).is_err());
+    }
+
+    #[test]
+    fn relative_points() {
+        assert_eq!(point("tomorrow 21:30").unwrap(), at("2026-12-30", "21:30"));
+        assert_eq!(point("+2d 09:00").unwrap(), at("2026-12-31", "09:00"));
+        assert_eq!(point("+3h").unwrap(), at("2026-12-29", "13:00"));
+        assert_eq!(point("09:30").unwrap(), at("2026-12-30", "09:30"));
+        assert!(point("2026-12-29 09:30").is_err());
+    }
+
+    #[test]
+    fn every_from_spec() {
+        let parsed = spec("every 1d12h from 2026-12-30 22:00").unwrap();
+        assert_eq!(parsed.start(), at("2026-12-30", "22:00"));
+        assert_eq!(parsed.interval().to_duration().unwrap(), Duration::hours(36));
+
+        let parsed = spec("2026-12-30 22:00 every 90m").unwrap();
+        assert_eq!(parsed.start(), at("2026-12-30", "22:00"));
+        assert_eq!(parsed.interval().to_duration().unwrap(), Duration::minutes(90));
+
+        assert!(spec("every 45m from 2026-12-30 22:00").is_err());
+        assert!(spec("every 0m from 2026-12-30 22:00").is_err());
+        assert!(spec("every 1d").is_err());
+    }
+
+    #[test]
+    fn duration_carries_over() {
+        let interval = parse_duration("90m").unwrap();
+        assert_eq!((interval.hours, interval.minutes), (1, 30));
+        let interval = parse_duration("1d36h").unwrap();
+        assert_eq!((interval.days, interval.hours), (2, 12));
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("1x").is_err());
+    }
+}
@@ -9,8 +9,52 @@ use thirtyfour::WebDriver;
 
 #[tokio::main]
 async fn main() -> Result<()> {
+    // 命令列模式：auto_esj lint <書籍路徑>，有錯誤時以非零狀態碼結束
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some("lint") {
+        let book_path = match args.get(2) {
+            Some(path) => path.clone(),
+            None => get_input("請輸入書籍檔案路徑: "),
+        };
+        let report = book::lint(&book_path)?;
+        report.print();
+        if report.has_errors() {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+    // 命令列模式：auto_esj book add|list|show|edit|remove ...，不需啟動瀏覽器
+    if args.get(1).map(String::as_str) == Some("book") {
+        return book::manage(&args[2..]);
+    }
+    // 命令列模式：auto_esj queue list|pause|resume|cancel，queue run 啟動常駐發布程序
+    if args.get(1).map(String::as_str) == Some("queue") {
+        if args.get(2).map(String::as_str) == Some("run") {
+            return run_queue().await;
+        }
+        return book::manage_queue(&args[2..]).await;
+    }
+    // 命令列模式：auto_esj diff <書籍路徑> [--json]，唯讀比對本地與遠端目錄
+    if args.get(1).map(String::as_str) == Some("diff") {
+        return run_diff(&args[2..]).await;
+    }
+    // 命令列模式：auto_esj calendar [書籍] [--output 檔案]，匯出 .ics 發布行事曆
+    if args.get(1).map(String::as_str) == Some("calendar") {
+        return book::export_calendar(&args[2..]);
+    }
+    // 命令列模式：auto_esj schedule [書籍編號]，列出所有書籍尚未到達的預約
+    if args.get(1).map(String::as_str) == Some("schedule") {
+        return book::reservations(&args[2..]);
+    }
+
+    // 選項：auto_esj --schedule "every 1d from 2027-01-03 20:00"，發布時不再詢問預約時間
+    let schedule_spec = args.iter()
+        .position(|arg| arg == "--schedule")
+        .map(|i| args.get(i + 1).cloned().context("--schedule 缺少排程表達式"))
+        .transpose()?;
+
     let (driver, driver_process, mut config) = esj::start_driver().await.context("無法啟動瀏覽器")?;
-    let result = execute(&driver, &config).await;
+    let result = execute(&driver, &config, schedule_spec.as_deref()).await;
     if let Err(e) = result {
         println!("{}", e);
     }
@@ -19,13 +63,14 @@ async fn main() -> Result<()> {
     Ok(())
 }
 
-async fn execute(driver: &WebDriver, config: &Config) -> Result<()> {
+async fn execute(driver: &WebDriver, config: &Config, schedule_spec: Option<&str>) -> Result<()> {
     let result: Result<()> = loop {
         println!("1. 發布文章");
         println!("2. 編輯文章");
         println!("3. 目錄整合");
         println!("4. 目錄同步");
-        println!("5. 退出");
+        println!("5. 書籍檢查");
+        println!("6. 退出");
         let choice = get_input("請選擇操作: ");
         match choice.as_str() {
             "1" => {
@@ -33,11 +78,23 @@ async fn execute(driver: &WebDriver, config: &Config) -> Result<()> {
                 let (mut book, chapters) = book::processor(book::WORK_DIR).await?;
 
                 // --- 修改：呼叫新的調度函數 ---
-                let chapter_count = chapters.len(); // 取得章節數量
-                let schedules = book::determine_schedules(chapter_count)?; // 調用新的主調度函數
+                let (schedules, rule) = book::determine_schedules(&book, &chapters, schedule_spec)?; // 稿件已指定的預約時間優先
                 // --- 结束修改 ---
 
-                esj::execute_publish(&mut book, &chapters, &driver, &config, schedules).await?;
+                // 發布前可逐列調整預約時間，確認後才送出
+                match book::review_schedules(&book, &chapters, schedules)? {
+                    // 網站預約無法修改，可改由本地佇列在時間到達時發布
+                    Some(schedules) if schedules.iter().any(Option::is_some)
+                        && get_input("是否改用本地發布佇列 (y/n，默認 n): ").to_lowercase() == "y" => {
+                        let count = book::Queue::update(|queue| queue.enqueue(&book, &chapters, &schedules)).await?;
+                        println!("已將 {} 個章節加入發布佇列，請執行 auto_esj queue run 啟動發布程序", count);
+                    }
+                    Some(schedules) => {
+                        esj::execute_publish(&mut book, &chapters, &driver, &config, schedules.clone()).await?;
+                        book.record_published(&chapters, &schedules, rule)?;
+                    }
+                    None => println!("已取消發布"),
+                }
                 fs::remove_dir_all(book::WORK_DIR)?;
             },
             "2" => {
@@ -45,6 +102,7 @@ async fn execute(driver: &WebDriver, config: &Config) -> Result<()> {
                 let (book, chapters) = book::processor(book::WORK_DIR).await?;
                 // esj::execute_edit(&book, &chapters, &driver, &config).await?;
                 esj::execute_edit(&book, &chapters, &driver, &config).await?;
+                book.record_edited(&chapters)?;
                 fs::remove_dir_all(book::WORK_DIR)?;
             },
             "3" => {
@@ -56,7 +114,11 @@ async fn execute(driver: &WebDriver, config: &Config) -> Result<()> {
                 let book = book::Config::build(&book_path)?;
                 esj::chapters_sync(&book, &book_path, config, driver).await?;
             }
-            "5" => break Err(anyhow::anyhow!("已退出")),
+            "5" => {
+                let report = book::lint(&get_input("請輸入書籍檔案路徑: "))?;
+                report.print();
+            }
+            "6" => break Err(anyhow::anyhow!("已退出")),
             _ => println!("無效的選項"),
         }
         get_input("按任意鍵繼續");
@@ -65,3 +127,92 @@ async fn execute(driver: &WebDriver, config: &Config) -> Result<()> {
 
     result
 }
+
+// 取得遠端目錄後與本地稿件、章節列表比對，不修改任何檔案
+async fn run_diff(args: &[String]) -> Result<()> {
+    let json = args.iter().any(|arg| arg == "--json");
+    let book_path = match args.iter().find(|arg| *arg != "--json") {
+        Some(path) => path.clone(),
+        None => get_input("請輸入書籍檔案路徑: "),
+    };
+    let book = book::Config::find(&book_path)?;
+    let (driver, driver_process, mut config) = esj::start_driver().await.context("無法啟動瀏覽器")?;
+    let remote = esj::fetch_remote_chapters(&book, &config, &driver).await;
+    // 不論取得目錄是否成功，都要關閉瀏覽器
+    let cookies = config.set_cookies(&driver).await;
+    esj::stop_driver(driver, driver_process).await?;
+    let report = book.diff(&remote?, &book_path)?;
+    cookies?;
+    if json {
+        println!("{}", report.to_json()?);
+    } else {
+        report.print();
+    }
+    Ok(())
+}
+
+// 本地發布佇列的常駐程序：定期檢查到期的章節並立即發布 (不使用網站預約)
+async fn run_queue() -> Result<()> {
+    let settings = book::QueueSettings::load()?;
+    let interrupted = book::Queue::update(|queue| Ok(queue.recover())).await?;
+    if interrupted > 0 {
+        println!("警告：有 {} 個項目在上次發布時中斷，已標記為失敗", interrupted);
+    }
+    println!("發布佇列已啟動，按 Ctrl+C 結束");
+
+    loop {
+        // 每次重新讀取，讓 queue pause/resume/cancel 的修改立即生效
+        let batches = book::Queue::update(|queue| Ok(queue.take_due(&settings))).await?;
+        for mut batch in batches {
+            // 稿件已變更的章節不發布，其餘章節照常發布
+            let result = match batch.verify() {
+                Ok(rejected) => {
+                    book::Queue::update(|queue| {
+                        queue.reject(&rejected);
+                        Ok(())
+                    }).await?;
+                    rejected.iter().for_each(|(_, reason)| println!("{}", reason));
+                    if batch.is_empty() {
+                        continue;
+                    }
+                    publish_batch(&batch).await
+                }
+                Err(e) => Err(e),
+            };
+            if let Err(e) = &result {
+                println!("發布 {} 第 {:?} 章失敗: {}", batch.book_path(), batch.indices(), e);
+            }
+            let error = result.err().map(|e| e.to_string());
+            book::Queue::update(|queue| {
+                queue.finish(&batch, error);
+                Ok(())
+            }).await?;
+        }
+        tokio::time::sleep(settings.poll_interval()).await;
+    }
+}
+
+async fn publish_batch(batch: &book::QueueBatch) -> Result<()> {
+    fs::create_dir_all(book::WORK_DIR).context("無法創建臨時目錄")?;
+    let result = publish_prepared(batch).await;
+    // 不論成功與否都清除臨時目錄，清除失敗不影響發布結果
+    if let Err(e) = fs::remove_dir_all(book::WORK_DIR) {
+        println!("警告：無法刪除臨時目錄 {}: {}", book::WORK_DIR, e);
+    }
+    result
+}
+
+async fn publish_prepared(batch: &book::QueueBatch) -> Result<()> {
+    let (mut book, chapters) = book::prepare(batch.book_path(), batch.indices(), book::WORK_DIR).await?;
+    let (driver, driver_process, mut config) = esj::start_driver().await.context("無法啟動瀏覽器")?;
+    // 時間已到，直接發布
+    let result = esj::execute_publish(&mut book, &chapters, &driver, &config, vec![None; chapters.len()]).await;
+    // 不論發布或儲存 cookies 是否成功，都要關閉瀏覽器
+    let cookies = config.set_cookies(&driver).await;
+    esj::stop_driver(driver, driver_process).await?;
+    result?;
+    cookies?;
+    book.record_published(&chapters, &batch.schedules(), None)?;
+    println!("已發布 {} 第 {:?} 章", batch.book_path(), batch.indices());
+    Ok(())
+}
@@ -15,11 +15,16 @@ pub fn get_executable_name(base_name: &str) -> String {
 }
 
 pub fn get_input(prompt: &str) -> String {
+    get_raw_input(prompt).trim_matches('"').to_string()
+}
+
+// 保留輸入中的引號，供引號本身有意義的輸入使用
+pub fn get_raw_input(prompt: &str) -> String {
     print!("{}", prompt);
     io::stdout().flush().unwrap();
     let mut input = String::new();
     io::stdin().read_line(&mut input).unwrap();
-    input.trim().trim_matches('"').to_string()
+    input.trim().to_string()
 }
 
 pub fn save<T>(section_head: &str, section_content: &T) -> Result<()>
@@ -62,6 +67,27 @@ where T: DeserializeOwned
     section_table.clone().try_into::<T>().with_context(|| format!("{section_head}配置轉換失敗"))
 }
 
+// 配置文件或區段不存在時回傳 None，區段內容格式錯誤時回傳錯誤
+pub fn load_optional<T>(section_head: &str) -> Result<Option<T>>
+where T: DeserializeOwned
+{
+    if !Path::new(CONFIG_FILE).exists() {
+        return Ok(None);
+    }
+    let config_str = fs::read_to_string(CONFIG_FILE)
+        .with_context(|| format!("配置文件{CONFIG_FILE}加載失败"))?;
+
+    let config_table: toml::Value = config_str.parse()
+        .with_context(|| format!("配置文件{CONFIG_FILE}解析失败"))?;
+
+    match config_table.get(section_head) {
+        Some(section_table) => section_table.clone().try_into::<T>()
+            .map(Some)
+            .with_context(|| format!("配置文件{CONFIG_FILE}的[{section_head}]區段格式錯誤")),
+        None => Ok(None),
+    }
+}
+
 pub fn copy_file(src: &Path, dest: &Path) -> Result<()> {
     if !src.exists() {
         return Err(anyhow::anyhow!("文件{:?}不存在", src));